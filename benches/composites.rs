@@ -0,0 +1,129 @@
+#[macro_use]
+extern crate criterion;
+#[macro_use]
+extern crate postgres_derive;
+extern crate postgres_derive_runtime;
+extern crate postgres;
+
+use criterion::{black_box, Criterion};
+use postgres::types::{Field, FromSql, Kind, ToSql, Type};
+
+#[derive(Debug, ToSql, FromSql)]
+#[postgres(name = "wide")]
+struct Wide {
+    reading_00: i32,
+    reading_01: i32,
+    reading_02: i32,
+    reading_03: String,
+    reading_04: i32,
+    reading_05: i32,
+    reading_06: i32,
+    reading_07: String,
+    reading_08: i32,
+    reading_09: i32,
+    reading_10: i32,
+    reading_11: String,
+    reading_12: i32,
+    reading_13: i32,
+    reading_14: i32,
+    reading_15: String,
+    reading_16: i32,
+    reading_17: i32,
+    reading_18: i32,
+    reading_19: String,
+    reading_20: i32,
+    reading_21: i32,
+    reading_22: i32,
+    reading_23: String,
+    reading_24: i32,
+    reading_25: i32,
+    reading_26: i32,
+    reading_27: String,
+    reading_28: i32,
+    reading_29: i32,
+    reading_30: i32,
+    reading_31: String,
+}
+
+fn wide_type() -> Type {
+    let int4 = Type::from_oid(23).unwrap();
+    let text = Type::from_oid(25).unwrap();
+    let fields = (0..32)
+        .map(|i| {
+            let type_ = if i % 4 == 3 { &text } else { &int4 };
+            Field::new(format!("reading_{:02}", i), type_.clone())
+        })
+        .collect();
+    Type::_new("wide".to_owned(), 16384, Kind::Composite(fields), "public".to_owned())
+}
+
+fn wide_value() -> Wide {
+    Wide {
+        reading_00: 0,
+        reading_01: 1,
+        reading_02: 2,
+        reading_03: "value 3".to_owned(),
+        reading_04: 4,
+        reading_05: 5,
+        reading_06: 6,
+        reading_07: "value 7".to_owned(),
+        reading_08: 8,
+        reading_09: 9,
+        reading_10: 10,
+        reading_11: "value 11".to_owned(),
+        reading_12: 12,
+        reading_13: 13,
+        reading_14: 14,
+        reading_15: "value 15".to_owned(),
+        reading_16: 16,
+        reading_17: 17,
+        reading_18: 18,
+        reading_19: "value 19".to_owned(),
+        reading_20: 20,
+        reading_21: 21,
+        reading_22: 22,
+        reading_23: "value 23".to_owned(),
+        reading_24: 24,
+        reading_25: 25,
+        reading_26: 26,
+        reading_27: "value 27".to_owned(),
+        reading_28: 28,
+        reading_29: 29,
+        reading_30: 30,
+        reading_31: "value 31".to_owned(),
+    }
+}
+
+fn to_sql(c: &mut Criterion) {
+    let type_ = wide_type();
+    let value = wide_value();
+    let mut buf = vec![];
+
+    c.bench_function("to_sql wide composite", move |b| {
+        b.iter(|| {
+            buf.clear();
+            value.to_sql(&type_, &mut buf).unwrap();
+        })
+    });
+}
+
+fn from_sql(c: &mut Criterion) {
+    let type_ = wide_type();
+    let mut buf = vec![];
+    wide_value().to_sql(&type_, &mut buf).unwrap();
+
+    c.bench_function("from_sql wide composite", move |b| {
+        b.iter(|| Wide::from_sql(&type_, black_box(&buf)).unwrap())
+    });
+}
+
+fn accepts(c: &mut Criterion) {
+    let type_ = wide_type();
+
+    c.bench_function("accepts wide composite", move |b| {
+        b.iter(|| <Wide as ToSql>::accepts(black_box(&type_)))
+    });
+}
+
+criterion_group!(benches, to_sql, from_sql, accepts);
+criterion_main!(benches);
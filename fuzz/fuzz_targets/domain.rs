@@ -0,0 +1,17 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate postgres;
+extern crate postgres_derive_fuzz;
+
+use postgres::types::{FromSql, ToSql};
+use postgres_derive_fuzz::SessionId;
+
+fuzz_target!(|data: &[u8]| {
+    if let Some((type_, buf)) = postgres_derive_fuzz::split(data) {
+        if let Ok(value) = SessionId::from_sql(&type_, buf) {
+            let mut buf = vec![];
+            let _ = value.to_sql(&type_, &mut buf);
+        }
+    }
+});
@@ -0,0 +1,122 @@
+//! Types shared by the fuzz targets.
+//!
+//! Each target decodes its input as one of the derived types, using the first byte of the input to
+//! pick the `Type` it is decoded as. Most of the `Type`s don't match the Rust type, and decoding
+//! must fail with an error rather than panic.
+#[macro_use]
+extern crate postgres_derive;
+extern crate postgres_derive_runtime;
+extern crate postgres;
+
+use postgres::types::{Field, Kind, Type};
+
+#[derive(Debug, ToSql, FromSql)]
+#[postgres(name = "mood")]
+pub enum Mood {
+    #[postgres(name = "sad")]
+    Sad,
+    #[postgres(name = "ok")]
+    Ok,
+    #[postgres(name = "happy")]
+    Happy,
+}
+
+#[derive(Debug, ToSql, FromSql)]
+#[postgres(name = "session_id")]
+pub struct SessionId(pub String);
+
+#[derive(Debug, ToSql, FromSql)]
+#[postgres(name = "inventory_item")]
+pub struct InventoryItem {
+    pub name: String,
+    pub supplier_id: i32,
+    pub price: Option<i32>,
+    pub mood: Mood,
+    pub session_id: Option<SessionId>,
+}
+
+fn composite(oid: u32, fields: &[(&str, &Type)]) -> Type {
+    let fields = fields
+        .iter()
+        .map(|&(name, type_)| Field::new(name.to_owned(), type_.clone()))
+        .collect();
+    Type::_new(
+        "inventory_item".to_owned(),
+        oid,
+        Kind::Composite(fields),
+        "public".to_owned(),
+    )
+}
+
+/// Returns the `Type`s values are decoded as.
+///
+/// Every type has a distinct OID, as the generated impls cache information about types by OID.
+pub fn types() -> Vec<Type> {
+    let int4 = Type::from_oid(23).unwrap();
+    let text = Type::from_oid(25).unwrap();
+    let mood = Type::_new(
+        "mood".to_owned(),
+        16384,
+        Kind::Enum(vec!["sad".to_owned(), "ok".to_owned(), "happy".to_owned()]),
+        "public".to_owned(),
+    );
+    let session_id = Type::_new(
+        "session_id".to_owned(),
+        16385,
+        Kind::Domain(text.clone()),
+        "public".to_owned(),
+    );
+
+    let item = composite(
+        16386,
+        &[
+            ("name", &text),
+            ("supplier_id", &int4),
+            ("price", &int4),
+            ("mood", &mood),
+            ("session_id", &session_id),
+        ],
+    );
+    let reordered = composite(
+        16387,
+        &[
+            ("session_id", &session_id),
+            ("mood", &mood),
+            ("price", &int4),
+            ("supplier_id", &int4),
+            ("name", &text),
+        ],
+    );
+    let missing_field = composite(16388, &[("name", &text), ("supplier_id", &int4)]);
+    let duplicate_field = composite(
+        16389,
+        &[
+            ("name", &text),
+            ("name", &text),
+            ("price", &int4),
+            ("mood", &mood),
+            ("session_id", &session_id),
+        ],
+    );
+    let unknown_field = composite(16390, &[("name", &text), ("unknown", &int4)]);
+
+    vec![
+        int4,
+        text,
+        mood,
+        session_id,
+        item,
+        reordered,
+        missing_field,
+        duplicate_field,
+        unknown_field,
+    ]
+}
+
+/// Splits fuzzer input into the `Type` to decode it as and the value to decode.
+pub fn split(data: &[u8]) -> Option<(Type, &[u8])> {
+    let (&index, buf) = data.split_first()?;
+    let types = types();
+    let type_ = types[index as usize % types.len()].clone();
+    Some((type_, buf))
+}
@@ -0,0 +1,47 @@
+//! Decoding of the binary format of bit strings.
+//!
+//! A `bit` or `varbit` value consists of its length in bits followed by the bits, packed most
+//! significant bit first and padded with zeros to a whole number of bytes.
+use error::{DecodeError, DecodeErrorKind};
+use record;
+
+/// A decoded bit string.
+pub struct BitString<'a> {
+    len: usize,
+    bytes: &'a [u8],
+}
+
+impl<'a> BitString<'a> {
+    /// Reads a bit string of the specified Postgres type.
+    pub fn read(mut buf: &'a [u8], type_: &str) -> Result<BitString<'a>, DecodeError> {
+        let start = buf;
+        let invalid = || DecodeError::new(type_, DecodeErrorKind::InvalidBufferSize).__at(start);
+
+        let len = match record::read_be_i32(&mut buf) {
+            Some(len) if len >= 0 => len as usize,
+            _ => return Err(invalid()),
+        };
+        if buf.len() != (len + 7) / 8 {
+            return Err(invalid());
+        }
+
+        Ok(BitString { len, bytes: buf })
+    }
+
+    /// Returns the number of bits in the string.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Determines if the string contains no bits.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the bit at the specified position, counting from the left.
+    ///
+    /// Positions past the end of the string are unset.
+    pub fn get(&self, bit: usize) -> bool {
+        bit < self.len && self.bytes[bit / 8] & (0x80 >> (bit % 8)) != 0
+    }
+}
@@ -0,0 +1,69 @@
+//! Caching of the results of `accepts`.
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+const SLOTS: usize = 32;
+
+// Each slot holds `fingerprint << 34 | oid << 2 | PRESENT | accepted`, or 0 if it is empty.
+const PRESENT: u64 = 0b10;
+const ACCEPTED: u64 = 0b01;
+const FINGERPRINT_SHIFT: u32 = 34;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY: AtomicU64 = AtomicU64::new(0);
+
+/// A fixed size cache of whether composite types are accepted.
+///
+/// Entries are keyed by the OID of the type and a hash of the names and type OIDs of its fields.
+/// `ALTER TYPE` keeps the OID of a type, so the fields are part of the key to make sure a result
+/// cached before the type was altered is not returned for its new definition. Types nested in the
+/// fields are only identified by their OIDs.
+///
+/// Each OID maps to a single slot, and a type whose slot is taken by another evicts it. Lookups
+/// and insertions are lock-free.
+pub struct AcceptsCache {
+    slots: [AtomicU64; SLOTS],
+}
+
+impl AcceptsCache {
+    /// Creates an empty cache.
+    pub const fn new() -> AcceptsCache {
+        AcceptsCache {
+            slots: [EMPTY; SLOTS],
+        }
+    }
+
+    /// Returns the cached result for the type with the specified OID and fields, computing and
+    /// caching it with `f` if it is not present.
+    ///
+    /// The fields are given as pairs of their names and type OIDs.
+    pub fn get_or_insert_with<'a, I, F>(&self, oid: u32, fields: I, f: F) -> bool
+    where
+        I: IntoIterator<Item = (&'a str, u32)>,
+        F: FnOnce() -> bool,
+    {
+        let mut hasher = DefaultHasher::new();
+        for (name, oid) in fields {
+            name.hash(&mut hasher);
+            oid.hash(&mut hasher);
+        }
+        let key = hasher.finish() << FINGERPRINT_SHIFT | u64::from(oid) << 2 | PRESENT;
+
+        let slot = &self.slots[oid as usize % SLOTS];
+        let entry = slot.load(Ordering::Relaxed);
+        if entry & !ACCEPTED == key {
+            return entry & ACCEPTED != 0;
+        }
+
+        let accepted = f();
+        slot.store(key | if accepted { ACCEPTED } else { 0 }, Ordering::Relaxed);
+        accepted
+    }
+}
+
+impl Default for AcceptsCache {
+    fn default() -> AcceptsCache {
+        AcceptsCache::new()
+    }
+}
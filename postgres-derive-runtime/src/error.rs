@@ -0,0 +1,329 @@
+//! Errors returned when encoding and decoding derived types.
+use std::error::Error;
+use std::fmt;
+
+use record::{self, BoxError};
+
+/// An error decoding a derived type.
+///
+/// Errors decoding nested types carry the path to the value which failed to decode, starting from
+/// the outermost type, like `order.items[3].price`.
+#[derive(Debug)]
+pub struct DecodeError(Box<Inner>);
+
+#[derive(Debug)]
+struct Inner {
+    root: String,
+    path: Vec<PathSegment>,
+    type_: String,
+    kind: DecodeErrorKind,
+    cause: Option<BoxError>,
+    // The address of the value which failed to decode, used to find its index when it is an
+    // element of an array.
+    position: Option<usize>,
+}
+
+/// A step in the path to a value which failed to decode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    /// A field of a composite type.
+    Field(String),
+    /// An element of an array, indexed from 0.
+    Index(usize),
+}
+
+impl fmt::Display for PathSegment {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PathSegment::Field(ref field) => write!(fmt, ".{}", field),
+            PathSegment::Index(index) => write!(fmt, "[{}]", index),
+        }
+    }
+}
+
+/// The reason a value failed to decode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DecodeErrorKind {
+    /// The type is not a composite type.
+    NotComposite,
+    /// The value has a different number of fields than the type.
+    InvalidFieldCount {
+        /// The number of fields of the type.
+        expected: usize,
+        /// The number of fields of the value.
+        actual: usize,
+    },
+    /// A field of the value has a different type than the field of the type.
+    UnexpectedOid {
+        /// The OID of the type of the field.
+        expected: u32,
+        /// The OID in the value.
+        actual: u32,
+    },
+    /// The value ended early.
+    InvalidBufferSize,
+    /// Bytes remained after the last field of the value.
+    TrailingBytes(usize),
+    /// The type has a field not present in the Rust type.
+    UnexpectedField(String),
+    /// The value is missing a field of the Rust type.
+    MissingField(String),
+    /// A value is `NULL` but the Rust type is not an `Option`.
+    UnexpectedNull,
+    /// A value failed to decode as its Rust type. The underlying error is its `cause`.
+    InvalidValue,
+    /// A value is not a label, or for integer enums a code, of the enum.
+    InvalidVariant(String),
+    /// An element of a set appears more than once.
+    DuplicateElement,
+}
+
+impl fmt::Display for DecodeErrorKind {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeErrorKind::NotComposite => fmt.write_str("type is not a composite"),
+            DecodeErrorKind::InvalidFieldCount { expected, actual } => {
+                write!(fmt, "invalid field count: expected {}, got {}", expected, actual)
+            }
+            DecodeErrorKind::UnexpectedOid { expected, actual } => {
+                write!(fmt, "unexpected OID: expected {}, got {}", expected, actual)
+            }
+            DecodeErrorKind::InvalidBufferSize => fmt.write_str("invalid buffer size"),
+            DecodeErrorKind::TrailingBytes(len) => write!(fmt, "{} trailing bytes", len),
+            DecodeErrorKind::UnexpectedField(ref field) => {
+                write!(fmt, "unexpected field `{}`", field)
+            }
+            DecodeErrorKind::MissingField(ref field) => write!(fmt, "missing field `{}`", field),
+            DecodeErrorKind::UnexpectedNull => fmt.write_str("unexpected NULL"),
+            DecodeErrorKind::InvalidValue => fmt.write_str("invalid value"),
+            DecodeErrorKind::InvalidVariant(ref label) => {
+                write!(fmt, "invalid variant `{}`", label)
+            }
+            DecodeErrorKind::DuplicateElement => fmt.write_str("duplicate element"),
+        }
+    }
+}
+
+impl DecodeError {
+    /// Creates an error decoding a value of the specified Postgres type.
+    pub fn new(type_: &str, kind: DecodeErrorKind) -> DecodeError {
+        DecodeError(Box::new(Inner {
+            root: type_.to_owned(),
+            path: vec![],
+            type_: type_.to_owned(),
+            kind,
+            cause: None,
+            position: None,
+        }))
+    }
+
+    /// Returns the name of the outermost Postgres type being decoded.
+    pub fn root(&self) -> &str {
+        &self.0.root
+    }
+
+    /// Returns the path from the outermost type to the value which failed to decode.
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.0.path
+    }
+
+    /// Returns the path to the value which failed to decode, like `order.items[3].price`.
+    pub fn path(&self) -> String {
+        let mut path = self.0.root.clone();
+        for segment in &self.0.path {
+            path.push_str(&segment.to_string());
+        }
+        path
+    }
+
+    /// Returns the name of the Postgres type of the value which failed to decode.
+    pub fn type_(&self) -> &str {
+        &self.0.type_
+    }
+
+    /// Returns the reason the value failed to decode.
+    pub fn kind(&self) -> &DecodeErrorKind {
+        &self.0.kind
+    }
+
+    /// Returns the underlying error, if any.
+    pub fn cause(&self) -> Option<&(dyn Error + 'static + Sync + Send)> {
+        self.0.cause.as_deref()
+    }
+
+    pub(crate) fn in_field(mut self, field: &str) -> DecodeError {
+        self.0.path.insert(0, PathSegment::Field(field.to_owned()));
+        self
+    }
+
+    /// Wraps an error decoding a field of a composite type.
+    ///
+    /// `value` is the raw value of the field.
+    #[doc(hidden)]
+    pub fn __field(
+        composite: &str,
+        field: &str,
+        field_type: &str,
+        value: Option<&[u8]>,
+        cause: BoxError,
+    ) -> DecodeError {
+        let mut error = match cause.downcast::<DecodeError>() {
+            Ok(error) => {
+                let mut error = *error;
+                if let (Some(position), Some(value)) = (error.0.position.take(), value) {
+                    // Values nested directly in the field start where it does.
+                    if position != value.as_ptr() as usize {
+                        if let Some(index) = record::array_index(value, position) {
+                            error.0.path.insert(0, PathSegment::Index(index));
+                        }
+                    }
+                }
+                error
+            }
+            Err(cause) => {
+                let kind = match value {
+                    Some(_) => DecodeErrorKind::InvalidValue,
+                    None => DecodeErrorKind::UnexpectedNull,
+                };
+                let mut error = DecodeError::new(field_type, kind);
+                error.0.cause = Some(cause);
+                error
+            }
+        };
+
+        error.0.root = composite.to_owned();
+        error.in_field(field)
+    }
+
+    /// Records the raw value which failed to decode.
+    #[doc(hidden)]
+    pub fn __at(mut self, buf: &[u8]) -> DecodeError {
+        self.0.position = Some(buf.as_ptr() as usize);
+        self
+    }
+
+    /// Returns an error for an enum value which isn't one of its labels.
+    #[doc(hidden)]
+    pub fn __invalid_variant(type_: &str, buf: &[u8]) -> BoxError {
+        let label = String::from_utf8_lossy(buf).into_owned();
+        Box::new(DecodeError::new(type_, DecodeErrorKind::InvalidVariant(label)).__at(buf))
+    }
+
+    /// Returns an error for an integer enum value which isn't one of its codes.
+    #[doc(hidden)]
+    pub fn __invalid_code(type_: &str, value: i64, buf: &[u8]) -> BoxError {
+        let kind = DecodeErrorKind::InvalidVariant(value.to_string());
+        Box::new(DecodeError::new(type_, kind).__at(buf))
+    }
+
+    /// Returns an error for an element of an array.
+    #[doc(hidden)]
+    pub fn __element(
+        array_type: &str,
+        element_type: &str,
+        index: usize,
+        kind: DecodeErrorKind,
+    ) -> BoxError {
+        let mut error = DecodeError::new(element_type, kind);
+        error.0.root = array_type.to_owned();
+        error.0.path.push(PathSegment::Index(index));
+        Box::new(error)
+    }
+
+    /// Returns an error for a value which decoded but failed to convert to the Rust type.
+    #[doc(hidden)]
+    pub fn __invalid_value<E>(type_: &str, cause: E) -> BoxError
+    where
+        E: Into<BoxError>,
+    {
+        let mut error = DecodeError::new(type_, DecodeErrorKind::InvalidValue);
+        error.0.cause = Some(cause.into());
+        Box::new(error)
+    }
+
+    /// Decodes a composite value with `f`, recording the raw value on error.
+    #[doc(hidden)]
+    pub fn __decode<'a, T, F>(buf: &'a [u8], f: F) -> Result<T, BoxError>
+    where
+        F: FnOnce(&'a [u8]) -> Result<T, DecodeError>,
+    {
+        f(buf).map_err(|e| Box::new(e.__at(buf)) as BoxError)
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "error decoding `{}`", self.path())?;
+        if !self.0.path.is_empty() {
+            write!(fmt, " of type `{}`", self.0.type_)?;
+        }
+        write!(fmt, ": {}", self.0.kind)?;
+        if let Some(ref cause) = self.0.cause {
+            write!(fmt, ": {}", cause)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for DecodeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self.0.cause {
+            Some(ref cause) => Some(&**cause),
+            None => None,
+        }
+    }
+}
+
+/// An error returned when a value is rejected by the `validate` function of its type.
+///
+/// Validation runs before encoding a value and after decoding one.
+#[derive(Debug)]
+pub struct ValidationError {
+    type_: String,
+    cause: BoxError,
+}
+
+impl ValidationError {
+    /// Creates an error rejecting a value of the specified Postgres type.
+    pub fn new<E>(type_: &str, cause: E) -> ValidationError
+    where
+        E: Into<BoxError>,
+    {
+        ValidationError {
+            type_: type_.to_owned(),
+            cause: cause.into(),
+        }
+    }
+
+    /// Returns the name of the Postgres type of the rejected value.
+    pub fn type_(&self) -> &str {
+        &self.type_
+    }
+
+    /// Returns the error returned by the validation function.
+    pub fn cause(&self) -> &(dyn Error + 'static + Sync + Send) {
+        &*self.cause
+    }
+
+    /// Boxes the result of a validation function.
+    #[doc(hidden)]
+    pub fn __check<E>(type_: &str, result: Result<(), E>) -> Result<(), BoxError>
+    where
+        E: Into<BoxError>,
+    {
+        result.map_err(|e| Box::new(ValidationError::new(type_, e)) as BoxError)
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "invalid value of type `{}`: {}", self.type_, self.cause)
+    }
+}
+
+impl Error for ValidationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&*self.cause)
+    }
+}
@@ -0,0 +1,77 @@
+//! Static metadata describing derived types.
+use postgres::types::Type;
+
+/// A type with static metadata describing its Postgres type.
+///
+/// This is implemented by `#[derive(PgTypeInfo)]`.
+pub trait PgTypeInfo {
+    /// Returns the metadata describing the type.
+    fn type_info() -> &'static TypeInfo;
+}
+
+/// Metadata describing a Postgres type.
+pub struct TypeInfo {
+    /// The name of the Postgres type.
+    pub name: &'static str,
+    /// The name of the Rust type.
+    pub rust_name: &'static str,
+    /// The kind of the Postgres type.
+    pub kind: TypeKind,
+}
+
+/// The kind of a Postgres type.
+pub enum TypeKind {
+    /// An enum.
+    Enum(EnumInfo),
+    /// A composite type, with its fields in declaration order.
+    Composite(&'static [FieldInfo]),
+    /// A domain.
+    Domain(DomainInfo),
+}
+
+impl TypeKind {
+    /// Returns the name of the kind, as used in the Postgres documentation.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            TypeKind::Enum(_) => "enum",
+            TypeKind::Composite(_) => "composite",
+            TypeKind::Domain(_) => "domain",
+        }
+    }
+}
+
+/// Metadata describing the variants of an enum.
+pub struct EnumInfo {
+    /// The variants, in declaration order.
+    pub variants: &'static [VariantInfo],
+    /// Whether the Postgres labels must be declared in the same order as the variants.
+    pub ordered: bool,
+}
+
+/// Metadata describing a variant of an enum.
+pub struct VariantInfo {
+    /// The Postgres label of the variant.
+    pub name: &'static str,
+    /// The name of the Rust variant.
+    pub rust_name: &'static str,
+}
+
+/// Metadata describing a field of a composite type.
+pub struct FieldInfo {
+    /// The name of the Postgres field.
+    pub name: &'static str,
+    /// The name of the Rust field.
+    pub rust_name: &'static str,
+    /// The Rust type of the field.
+    pub rust_type: &'static str,
+    /// A function checking if the Rust type of the field accepts a Postgres type.
+    pub accepts: fn(&Type) -> bool,
+}
+
+/// Metadata describing the value wrapped by a domain.
+pub struct DomainInfo {
+    /// The Rust type wrapped by the domain.
+    pub rust_type: &'static str,
+    /// A function checking if the wrapped Rust type accepts a Postgres type.
+    pub accepts: fn(&Type) -> bool,
+}
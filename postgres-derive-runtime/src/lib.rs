@@ -0,0 +1,26 @@
+//! Runtime support for code generated by `postgres-derive`.
+//!
+//! The `bits`, `cache`, `error`, `record` and `sql` support is independent of the postgres crate
+//! in use. The remaining support is built against postgres 0.15, and requires the default
+//! `postgres` feature.
+#[cfg(feature = "postgres")]
+extern crate postgres;
+
+pub use error::{DecodeError, ValidationError};
+#[cfg(feature = "postgres")]
+pub use info::PgTypeInfo;
+#[cfg(feature = "postgres")]
+pub use params::ToParams;
+pub use sql::{CreateType, SqlType};
+
+pub mod bits;
+pub mod cache;
+pub mod error;
+#[cfg(feature = "postgres")]
+pub mod info;
+#[cfg(feature = "postgres")]
+mod params;
+pub mod record;
+#[cfg(feature = "postgres")]
+pub mod schema;
+mod sql;
@@ -0,0 +1,12 @@
+use postgres::types::ToSql;
+
+/// A type which can be bound as a list of statement parameters, one per column.
+///
+/// This is implemented by `#[derive(ToParams)]`.
+pub trait ToParams {
+    /// Returns the names of the columns, in parameter order.
+    fn columns() -> &'static [&'static str];
+
+    /// Returns the value of each column, in the same order as `columns`.
+    fn to_params(&self) -> Vec<&dyn ToSql>;
+}
@@ -0,0 +1,299 @@
+//! Encoding and decoding of the binary format of composite values.
+//!
+//! A composite value consists of a field count followed by each field's type OID, length and
+//! value. A length of -1 indicates a `NULL` value.
+use std::error::Error;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+use error::{DecodeError, DecodeErrorKind};
+
+/// The error type returned when encoding or decoding a value.
+pub type BoxError = Box<dyn Error + Sync + Send>;
+
+/// Returns the header of a record with the specified number of fields.
+pub fn header(num_fields: usize) -> Result<[u8; 4], BoxError> {
+    if num_fields > i32::MAX as usize {
+        return Err("too many fields to transmit".into());
+    }
+
+    Ok(be_i32(num_fields as i32))
+}
+
+/// Returns the header of a field of the specified type.
+///
+/// The length of the field is left empty, and is filled in by `finish_field` once the value has
+/// been written.
+pub fn field_header(oid: u32) -> [u8; 8] {
+    let oid = be_i32(oid as i32);
+    [oid[0], oid[1], oid[2], oid[3], 0, 0, 0, 0]
+}
+
+/// Fills in the length of a field.
+///
+/// `field` contains the bytes of the field, starting at its header.
+pub fn finish_field(field: &mut [u8], is_null: bool) -> Result<(), BoxError> {
+    let len = if is_null {
+        -1
+    } else {
+        let len = field.len() - 8;
+        if len > i32::MAX as usize {
+            return Err("value too large to transmit".into());
+        }
+        len as i32
+    };
+
+    field[4..8].copy_from_slice(&be_i32(len));
+    Ok(())
+}
+
+/// Reads the header of a record of the specified type, checking its number of fields.
+pub fn read_header(buf: &mut &[u8], type_: &str, num_fields: usize) -> Result<(), DecodeError> {
+    let actual = read_be_i32(buf).ok_or_else(|| invalid_buffer_size(type_))?;
+    if actual < 0 {
+        return Err(invalid_buffer_size(type_));
+    }
+    if actual as usize != num_fields {
+        let kind = DecodeErrorKind::InvalidFieldCount {
+            expected: num_fields,
+            actual: actual as usize,
+        };
+        return Err(DecodeError::new(type_, kind));
+    }
+
+    Ok(())
+}
+
+/// Reads a field of a record of the specified type, returning its value.
+pub fn read_field<'a>(
+    buf: &mut &'a [u8],
+    type_: &str,
+    field: &str,
+    oid: u32,
+) -> Result<Option<&'a [u8]>, DecodeError> {
+    let error = |kind| DecodeError::new(type_, kind).in_field(field);
+
+    let actual = read_be_i32(buf).ok_or_else(|| error(DecodeErrorKind::InvalidBufferSize))? as u32;
+    if actual != oid {
+        return Err(error(DecodeErrorKind::UnexpectedOid {
+            expected: oid,
+            actual,
+        }));
+    }
+
+    let len = read_be_i32(buf).ok_or_else(|| error(DecodeErrorKind::InvalidBufferSize))?;
+    if len < 0 {
+        return Ok(None);
+    }
+    if len as usize > buf.len() {
+        return Err(error(DecodeErrorKind::InvalidBufferSize));
+    }
+
+    let (head, tail) = buf.split_at(len as usize);
+    *buf = tail;
+    Ok(Some(head))
+}
+
+/// Checks that no bytes remain after the last field of a record of the specified type.
+pub fn read_end(buf: &[u8], type_: &str) -> Result<(), DecodeError> {
+    if buf.is_empty() {
+        Ok(())
+    } else {
+        Err(DecodeError::new(type_, DecodeErrorKind::TrailingBytes(buf.len())))
+    }
+}
+
+// Returns the index of the element of an array value starting at the address `position`.
+pub(crate) fn array_index(mut buf: &[u8], position: usize) -> Option<usize> {
+    let ndim = read_be_i32(&mut buf)?;
+    let _has_nulls = read_be_i32(&mut buf)?;
+    let _element_oid = read_be_i32(&mut buf)?;
+    if ndim < 0 {
+        return None;
+    }
+    for _ in 0..ndim {
+        let _len = read_be_i32(&mut buf)?;
+        let _lower_bound = read_be_i32(&mut buf)?;
+    }
+
+    let mut index = 0;
+    while !buf.is_empty() {
+        let len = read_be_i32(&mut buf)?;
+        if buf.as_ptr() as usize == position {
+            return Some(index);
+        }
+        if len > 0 {
+            if len as usize > buf.len() {
+                return None;
+            }
+            buf = &buf[len as usize..];
+        }
+        index += 1;
+    }
+
+    None
+}
+
+fn invalid_buffer_size(type_: &str) -> DecodeError {
+    DecodeError::new(type_, DecodeErrorKind::InvalidBufferSize)
+}
+
+/// A cache of the positions of the fields of composite types in a Rust struct.
+///
+/// Plans are keyed by the OID and field names of the composite type, so the field names of a type
+/// are only looked up in the struct the first time one of its values is encoded or decoded. A type
+/// which is altered after its plan is built keeps its OID, but gets a new plan. Lookups are
+/// lock-free, and plans are never evicted.
+pub struct PlanCache {
+    head: AtomicPtr<Plan>,
+}
+
+struct Plan {
+    oid: u32,
+    fields: Box<[String]>,
+    positions: Box<[usize]>,
+    next: *mut Plan,
+}
+
+impl PlanCache {
+    /// Creates an empty cache.
+    pub const fn new() -> PlanCache {
+        PlanCache {
+            head: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    /// Returns the plan for decoding the composite type with the specified name, OID and field
+    /// names.
+    ///
+    /// The `i`th entry of the plan is the position in `names` of the `i`th field of the type.
+    pub fn decode_plan<'a, I>(
+        &self,
+        type_: &str,
+        oid: u32,
+        names: &[&str],
+        fields: I,
+    ) -> Result<&[usize], DecodeError>
+    where
+        I: IntoIterator<Item = &'a str>,
+        I::IntoIter: Clone,
+    {
+        self.plan(oid, names, fields).map_err(|field| {
+            DecodeError::new(type_, DecodeErrorKind::UnexpectedField(field.to_owned()))
+        })
+    }
+
+    /// Returns the plan for encoding the composite type with the specified name, OID and field
+    /// names.
+    ///
+    /// The `i`th entry of the plan is the position in `names` of the `i`th field of the type.
+    pub fn encode_plan<'a, I>(
+        &self,
+        type_: &str,
+        oid: u32,
+        names: &[&str],
+        fields: I,
+    ) -> Result<&[usize], BoxError>
+    where
+        I: IntoIterator<Item = &'a str>,
+        I::IntoIter: Clone,
+    {
+        self.plan(oid, names, fields).map_err(|field| {
+            format!("error encoding `{}`: unexpected field `{}`", type_, field).into()
+        })
+    }
+
+    // Returns the name of the first field of the type not in `names` if there is no plan.
+    fn plan<'a, I>(&self, oid: u32, names: &[&str], fields: I) -> Result<&[usize], &'a str>
+    where
+        I: IntoIterator<Item = &'a str>,
+        I::IntoIter: Clone,
+    {
+        let fields = fields.into_iter();
+        let mut head = self.head.load(Ordering::Acquire);
+        if let Some(positions) = self.find(head, oid, fields.clone()) {
+            return Ok(positions);
+        }
+
+        let positions = fields
+            .clone()
+            .map(|field| names.iter().position(|name| *name == field).ok_or(field))
+            .collect::<Result<Vec<_>, _>>()?;
+        let plan = Box::into_raw(Box::new(Plan {
+            oid,
+            fields: fields.map(|field| field.to_owned()).collect(),
+            positions: positions.into_boxed_slice(),
+            next: head,
+        }));
+
+        // Another thread may have inserted plans since we last looked, so retry until the list is
+        // unchanged. Duplicate plans for the same type are harmless.
+        loop {
+            match self.head.compare_exchange(head, plan, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => break,
+                Err(new_head) => {
+                    head = new_head;
+                    unsafe {
+                        (*plan).next = head;
+                    }
+                }
+            }
+        }
+
+        unsafe { Ok(&(*plan).positions) }
+    }
+
+    fn find<'a, I>(&self, mut plan: *mut Plan, oid: u32, fields: I) -> Option<&[usize]>
+    where
+        I: Iterator<Item = &'a str> + Clone,
+    {
+        // Plans are only freed when the cache is dropped, so they live as long as `self`.
+        unsafe {
+            while let Some(p) = plan.as_ref() {
+                // Connections opened before a type was altered still use its old fields, so a
+                // type may have several plans at once
+                if p.oid == oid && p.fields.iter().map(|f| &**f).eq(fields.clone()) {
+                    return Some(&p.positions);
+                }
+                plan = p.next;
+            }
+        }
+        None
+    }
+}
+
+impl Default for PlanCache {
+    fn default() -> PlanCache {
+        PlanCache::new()
+    }
+}
+
+impl Drop for PlanCache {
+    fn drop(&mut self) {
+        let mut plan = *self.head.get_mut();
+        while !plan.is_null() {
+            let p = unsafe { Box::from_raw(plan) };
+            plan = p.next;
+        }
+    }
+}
+
+// The cache only hands out shared references to its plans, which are never mutated once
+// published.
+unsafe impl Send for PlanCache {}
+unsafe impl Sync for PlanCache {}
+
+fn be_i32(n: i32) -> [u8; 4] {
+    [(n >> 24) as u8, (n >> 16) as u8, (n >> 8) as u8, n as u8]
+}
+
+pub(crate) fn read_be_i32(buf: &mut &[u8]) -> Option<i32> {
+    if buf.len() < 4 {
+        return None;
+    }
+
+    let num = ((buf[0] as i32) << 24) | ((buf[1] as i32) << 16) | ((buf[2] as i32) << 8) |
+        (buf[3] as i32);
+    *buf = &buf[4..];
+    Some(num)
+}
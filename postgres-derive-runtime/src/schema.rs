@@ -0,0 +1,294 @@
+//! Verification of derived types against the types defined in a database.
+use std::fmt;
+use postgres::{self, Connection};
+use postgres::types::{Kind, Type};
+
+use info::{DomainInfo, EnumInfo, FieldInfo, PgTypeInfo, TypeInfo, TypeKind};
+
+/// A difference between a derived type and the type defined in the database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Difference {
+    /// No type with the expected name is visible in the database.
+    MissingType,
+    /// The database type is of a different kind.
+    WrongKind {
+        /// The kind of the derived type.
+        expected: &'static str,
+        /// The kind of the database type.
+        actual: String,
+    },
+    /// A label of the derived enum is not present in the database.
+    MissingLabel(String),
+    /// A label of the database enum is not present in the derived type.
+    ExtraLabel(String),
+    /// The labels of an ordered enum are declared in a different order in the database.
+    WrongLabelOrder {
+        /// The labels shared by both enums, in the order of the derived type.
+        expected: Vec<String>,
+        /// The labels shared by both enums, in the order of the database type.
+        actual: Vec<String>,
+    },
+    /// A field of the derived composite is not present in the database.
+    MissingField(String),
+    /// A field of the database composite is not present in the derived type.
+    ExtraField(String),
+    /// A field of the database composite has a type not accepted by the derived type.
+    WrongFieldType {
+        /// The name of the field.
+        field: String,
+        /// The name of the field's type in the database.
+        type_: String,
+    },
+    /// The base type of the database domain is not accepted by the derived type.
+    WrongBaseType(String),
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Difference::MissingType => fmt.write_str("type does not exist"),
+            Difference::WrongKind {
+                ref expected,
+                ref actual,
+            } => write!(fmt, "expected a {} type but found a {} type", expected, actual),
+            Difference::MissingLabel(ref label) => write!(fmt, "missing label `{}`", label),
+            Difference::ExtraLabel(ref label) => write!(fmt, "unexpected label `{}`", label),
+            Difference::WrongLabelOrder {
+                ref expected,
+                ref actual,
+            } => write!(
+                fmt,
+                "expected labels in the order {} but found {}",
+                expected.join(", "),
+                actual.join(", ")
+            ),
+            Difference::MissingField(ref field) => write!(fmt, "missing field `{}`", field),
+            Difference::ExtraField(ref field) => write!(fmt, "unexpected field `{}`", field),
+            Difference::WrongFieldType {
+                ref field,
+                ref type_,
+            } => write!(fmt, "field `{}` has unsupported type `{}`", field, type_),
+            Difference::WrongBaseType(ref type_) => {
+                write!(fmt, "domain has unsupported base type `{}`", type_)
+            }
+        }
+    }
+}
+
+/// The differences found for a single derived type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeDiff {
+    /// The name of the Postgres type.
+    pub name: &'static str,
+    /// The differences between the derived type and the type in the database.
+    pub differences: Vec<Difference>,
+}
+
+impl TypeDiff {
+    /// Determines if the derived type matches the type in the database.
+    pub fn is_empty(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+impl fmt::Display for TypeDiff {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "type `{}`", self.name)?;
+        if self.differences.is_empty() {
+            return fmt.write_str(": ok");
+        }
+        for difference in &self.differences {
+            write!(fmt, "\n    {}", difference)?;
+        }
+        Ok(())
+    }
+}
+
+/// A set of derived types to check against a database.
+#[derive(Default)]
+pub struct Registry {
+    types: Vec<&'static TypeInfo>,
+}
+
+impl Registry {
+    /// Creates an empty registry.
+    pub fn new() -> Registry {
+        Registry::default()
+    }
+
+    /// Adds a type to the registry.
+    pub fn register<T>(&mut self) -> &mut Registry
+    where
+        T: PgTypeInfo,
+    {
+        self.types.push(T::type_info());
+        self
+    }
+
+    /// Compares every registered type against the types visible to the connection.
+    ///
+    /// A diff is returned for each type, in registration order.
+    pub fn check(&self, conn: &Connection) -> postgres::Result<Vec<TypeDiff>> {
+        self.types.iter().map(|&info| check(conn, info)).collect()
+    }
+}
+
+/// Compares a derived type against the type of the same name visible to the connection.
+pub fn check(conn: &Connection, info: &'static TypeInfo) -> postgres::Result<TypeDiff> {
+    let mut differences = vec![];
+
+    let rows = conn.query(
+        "SELECT t.oid, t.typtype, n.nspname \
+         FROM pg_catalog.pg_type t \
+         INNER JOIN pg_catalog.pg_namespace n ON t.typnamespace = n.oid \
+         WHERE t.typname = $1 AND pg_catalog.pg_type_is_visible(t.oid)",
+        &[&info.name],
+    )?;
+
+    if rows.is_empty() {
+        differences.push(Difference::MissingType);
+    } else {
+        let row = rows.get(0);
+        let oid: u32 = row.get(0);
+        let typtype: i8 = row.get(1);
+        let schema: String = row.get(2);
+
+        match (&info.kind, typtype as u8) {
+            (TypeKind::Enum(ref enum_), b'e') => {
+                check_enum(conn, oid, enum_, &mut differences)?
+            }
+            (TypeKind::Composite(fields), b'c') => {
+                let type_ = lookup_type(conn, &schema, info.name)?;
+                check_composite(&type_, fields, &mut differences);
+            }
+            (TypeKind::Domain(domain), b'd') => {
+                let type_ = lookup_type(conn, &schema, info.name)?;
+                check_domain(&type_, domain, &mut differences);
+            }
+            (kind, typtype) => {
+                differences.push(Difference::WrongKind {
+                    expected: kind.name(),
+                    actual: kind_name(typtype).to_owned(),
+                });
+            }
+        }
+    }
+
+    Ok(TypeDiff {
+        name: info.name,
+        differences,
+    })
+}
+
+fn kind_name(typtype: u8) -> &'static str {
+    match typtype {
+        b'b' => "base",
+        b'c' => "composite",
+        b'd' => "domain",
+        b'e' => "enum",
+        b'p' => "pseudo",
+        b'r' => "range",
+        _ => "unknown",
+    }
+}
+
+// Preparing a query is the only way to have the connection resolve the full structure of a
+// non-builtin type.
+fn lookup_type(conn: &Connection, schema: &str, name: &str) -> postgres::Result<Type> {
+    let query = format!("SELECT NULL::{}.{}", quote_ident(schema), quote_ident(name));
+    let stmt = conn.prepare(&query)?;
+    Ok(stmt.columns()[0].type_().clone())
+}
+
+fn quote_ident(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "\"\""))
+}
+
+fn check_enum(
+    conn: &Connection,
+    oid: u32,
+    enum_: &EnumInfo,
+    differences: &mut Vec<Difference>,
+) -> postgres::Result<()> {
+    let rows = conn.query(
+        "SELECT enumlabel FROM pg_catalog.pg_enum WHERE enumtypid = $1 ORDER BY enumsortorder",
+        &[&oid],
+    )?;
+    let actual = rows.iter().map(|r| r.get(0)).collect::<Vec<String>>();
+
+    for variant in enum_.variants {
+        if !actual.iter().any(|l| l == variant.name) {
+            differences.push(Difference::MissingLabel(variant.name.to_owned()));
+        }
+    }
+    for label in &actual {
+        if !enum_.variants.iter().any(|v| v.name == label) {
+            differences.push(Difference::ExtraLabel(label.clone()));
+        }
+    }
+
+    if enum_.ordered {
+        let expected = enum_
+            .variants
+            .iter()
+            .filter(|v| actual.iter().any(|l| l == v.name))
+            .map(|v| v.name.to_owned())
+            .collect::<Vec<_>>();
+        let actual = actual
+            .into_iter()
+            .filter(|l| enum_.variants.iter().any(|v| v.name == l))
+            .collect::<Vec<_>>();
+        if expected != actual {
+            differences.push(Difference::WrongLabelOrder { expected, actual });
+        }
+    }
+
+    Ok(())
+}
+
+fn check_composite(type_: &Type, fields: &[FieldInfo], differences: &mut Vec<Difference>) {
+    let actual = match *type_.kind() {
+        Kind::Composite(ref fields) => fields,
+        _ => return,
+    };
+
+    for field in fields {
+        match actual.iter().find(|f| f.name() == field.name) {
+            Some(f) => {
+                if !(field.accepts)(f.type_()) {
+                    differences.push(Difference::WrongFieldType {
+                        field: field.name.to_owned(),
+                        type_: f.type_().name().to_owned(),
+                    });
+                }
+            }
+            None => differences.push(Difference::MissingField(field.name.to_owned())),
+        }
+    }
+    for f in actual {
+        if !fields.iter().any(|field| field.name == f.name()) {
+            differences.push(Difference::ExtraField(f.name().to_owned()));
+        }
+    }
+}
+
+// Like the derives, walks the domain chain to the first base type the wrapped type accepts, which
+// may itself be a domain.
+fn check_domain(type_: &Type, domain: &DomainInfo, differences: &mut Vec<Difference>) {
+    let mut base = match *type_.kind() {
+        Kind::Domain(ref base) => base,
+        _ => return,
+    };
+
+    loop {
+        if (domain.accepts)(base) {
+            return;
+        }
+        match *base.kind() {
+            Kind::Domain(ref inner) => base = inner,
+            _ => break,
+        }
+    }
+
+    differences.push(Difference::WrongBaseType(base.name().to_owned()));
+}
@@ -0,0 +1,65 @@
+/// A Rust type with a corresponding Postgres type.
+///
+/// This is implemented for the standard library types supported by `postgres`, and by
+/// `#[derive(CreateType)]`.
+pub trait SqlType {
+    /// Returns the Postgres type as it would appear in a column definition.
+    fn sql_type() -> String;
+}
+
+/// A type which can generate the DDL creating its Postgres type.
+///
+/// This is implemented by `#[derive(CreateType)]`.
+pub trait CreateType: SqlType {
+    /// Returns the `CREATE TYPE` or `CREATE DOMAIN` statement for the type.
+    fn create_type_sql() -> String;
+}
+
+macro_rules! simple_sql_type {
+    ($($t:ty => $name:expr,)*) => {
+        $(
+            impl SqlType for $t {
+                fn sql_type() -> String {
+                    $name.to_owned()
+                }
+            }
+        )*
+    }
+}
+
+simple_sql_type! {
+    bool => "BOOL",
+    i8 => "\"char\"",
+    i16 => "INT2",
+    i32 => "INT4",
+    i64 => "INT8",
+    u32 => "OID",
+    f32 => "FLOAT4",
+    f64 => "FLOAT8",
+    String => "TEXT",
+    Vec<u8> => "BYTEA",
+}
+
+impl SqlType for &str {
+    fn sql_type() -> String {
+        String::sql_type()
+    }
+}
+
+impl<T> SqlType for Option<T>
+where
+    T: SqlType,
+{
+    fn sql_type() -> String {
+        T::sql_type()
+    }
+}
+
+impl<T> SqlType for Vec<T>
+where
+    T: SqlType,
+{
+    fn sql_type() -> String {
+        format!("{}[]", T::sql_type())
+    }
+}
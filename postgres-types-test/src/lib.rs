@@ -0,0 +1 @@
+//! Tests of the code generated with the `postgres-types` feature, run against postgres 0.19.
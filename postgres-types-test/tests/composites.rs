@@ -0,0 +1,237 @@
+use postgres::types::WrongType;
+use postgres_derive::{FromSql, ToSql};
+use postgres_derive_runtime::error::DecodeErrorKind;
+use postgres_derive_runtime::DecodeError;
+use std::error::Error;
+
+mod util;
+
+#[test]
+fn defaults() {
+    #[derive(FromSql, ToSql, Debug, PartialEq)]
+    #[postgres(name = "inventory_item")]
+    struct InventoryItem {
+        price: Option<f64>,
+        name: String,
+        supplier_id: i32,
+    }
+
+    let mut conn = util::connect();
+    conn.batch_execute(
+        "CREATE TYPE pg_temp.inventory_item AS (
+                            name TEXT,
+                            supplier_id INT,
+                            price DOUBLE PRECISION
+                        );",
+    ).unwrap();
+
+    let item = InventoryItem {
+        price: Some(15.50),
+        name: "foobar".to_owned(),
+        supplier_id: 100,
+    };
+
+    let item_null = InventoryItem {
+        price: None,
+        name: "foobar".to_owned(),
+        supplier_id: 100,
+    };
+
+    util::test_type(
+        &mut conn,
+        "inventory_item",
+        &[
+            (item, "ROW('foobar', 100, 15.50)"),
+            (item_null, "ROW('foobar', 100, NULL)"),
+        ],
+    );
+}
+
+#[test]
+fn wrong_type() {
+    #[derive(FromSql, ToSql, Debug, PartialEq)]
+    #[postgres(name = "inventory_item")]
+    struct InventoryItem {
+        name: String,
+        supplier_id: i32,
+    }
+
+    let mut conn = util::connect();
+    conn.batch_execute(
+        "CREATE TYPE pg_temp.inventory_item AS (
+                            name TEXT,
+                            supplier_id TEXT
+                        );",
+    ).unwrap();
+
+    let item = InventoryItem {
+        name: "foobar".to_owned(),
+        supplier_id: 100,
+    };
+    let err = conn.execute("SELECT $1::inventory_item", &[&item])
+        .unwrap_err();
+    assert!(err.source().unwrap().is::<WrongType>());
+}
+
+#[test]
+fn decode_error() {
+    #[derive(FromSql, Debug)]
+    #[postgres(name = "item")]
+    #[allow(dead_code)]
+    struct Item {
+        name: String,
+        price: i32,
+    }
+
+    #[derive(FromSql, Debug)]
+    #[postgres(name = "order")]
+    #[allow(dead_code)]
+    struct Order {
+        id: i32,
+        items: Vec<Item>,
+    }
+
+    let mut conn = util::connect();
+    conn.batch_execute(
+        "CREATE TYPE pg_temp.item AS (name TEXT, price INT);
+         CREATE TYPE pg_temp.\"order\" AS (id INT, items pg_temp.item[]);",
+    ).unwrap();
+
+    let row = conn.query_one(
+        "SELECT ROW(1, ARRAY[ROW('foo', 1), ROW('bar', NULL)]::pg_temp.item[])::pg_temp.\"order\"",
+        &[],
+    ).unwrap();
+    let err = row.try_get::<_, Order>(0).unwrap_err();
+    let err = err.source().unwrap().downcast_ref::<DecodeError>().unwrap();
+    assert_eq!(err.path(), "order.items[1].price");
+    assert_eq!(*err.kind(), DecodeErrorKind::UnexpectedNull);
+}
+
+#[test]
+fn encode_error() {
+    use postgres::types::private::BytesMut;
+    use postgres::types::{Field, Kind, ToSql, Type};
+
+    #[derive(ToSql, Debug)]
+    #[postgres(name = "item")]
+    struct Item {
+        name: String,
+        price: i32,
+    }
+
+    // Encoding directly skips the `accepts` check that would reject the type
+    let type_ = Type::new(
+        "item".to_owned(),
+        0,
+        Kind::Composite(vec![
+            Field::new("name".to_owned(), Type::TEXT),
+            Field::new("quantity".to_owned(), Type::INT4),
+        ]),
+        "public".to_owned(),
+    );
+    let item = Item {
+        name: "foo".to_owned(),
+        price: 1,
+    };
+    let err = match item.to_sql(&type_, &mut BytesMut::new()) {
+        Ok(_) => panic!("expected an error"),
+        Err(err) => err,
+    };
+    assert!(!err.is::<DecodeError>());
+    assert_eq!(err.to_string(), "error encoding `item`: unexpected field `quantity`");
+}
+
+#[test]
+fn altered_type() {
+    #[derive(FromSql, ToSql, Debug, PartialEq)]
+    #[postgres(name = "altered_point")]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    // Connections cache the definitions of types, so the type can't be temporary
+    let mut conn = util::connect();
+    conn.batch_execute(
+        "DROP TYPE IF EXISTS altered_point;
+         CREATE TYPE altered_point AS (x INT, y INT);",
+    ).unwrap();
+
+    util::test_type(&mut conn, "altered_point", &[(Point { x: 1, y: 2 }, "ROW(1, 2)")]);
+
+    // Swapping the names of the fields keeps the OID of the type
+    conn.batch_execute(
+        "ALTER TYPE altered_point RENAME ATTRIBUTE x TO z;
+         ALTER TYPE altered_point RENAME ATTRIBUTE y TO x;
+         ALTER TYPE altered_point RENAME ATTRIBUTE z TO y;",
+    ).unwrap();
+
+    let mut conn = util::connect();
+    util::test_type(&mut conn, "altered_point", &[(Point { x: 2, y: 1 }, "ROW(1, 2)")]);
+
+    // Adding a field keeps the OID as well, but the type is no longer accepted
+    conn.execute("ALTER TYPE altered_point ADD ATTRIBUTE z INT", &[])
+        .unwrap();
+
+    let mut conn = util::connect();
+    let err = conn.execute("SELECT $1::altered_point", &[&Point { x: 1, y: 2 }])
+        .unwrap_err();
+    assert!(err.source().unwrap().is::<WrongType>());
+
+    let row = conn.query_one("SELECT ROW(1, 2, 3)::altered_point", &[]).unwrap();
+    let err = row.try_get::<_, Point>(0).unwrap_err();
+    assert!(err.source().unwrap().is::<WrongType>());
+
+    conn.execute("DROP TYPE altered_point", &[]).unwrap();
+}
+
+#[test]
+fn with() {
+    mod cents {
+        use postgres::types::private::BytesMut;
+        use postgres::types::{FromSql, IsNull, ToSql, Type};
+        use std::error::Error;
+
+        #[derive(Debug, PartialEq)]
+        pub struct Cents(pub i32);
+
+        pub fn to_sql(
+            value: &Cents,
+            type_: &Type,
+            buf: &mut BytesMut,
+        ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+            value.0.to_sql(type_, buf)
+        }
+
+        pub fn from_sql(type_: &Type, raw: &[u8]) -> Result<Cents, Box<dyn Error + Sync + Send>> {
+            i32::from_sql(type_, raw).map(Cents)
+        }
+
+        pub fn accepts(type_: &Type) -> bool {
+            <i32 as ToSql>::accepts(type_)
+        }
+    }
+
+    #[derive(FromSql, ToSql, Debug, PartialEq)]
+    #[postgres(name = "inventory_item")]
+    struct InventoryItem {
+        name: String,
+        #[postgres(with = "cents")]
+        price: cents::Cents,
+    }
+
+    let mut conn = util::connect();
+    conn.batch_execute(
+        "CREATE TYPE pg_temp.inventory_item AS (
+                            name TEXT,
+                            price INT
+                        );",
+    ).unwrap();
+
+    let item = InventoryItem {
+        name: "foobar".to_owned(),
+        price: cents::Cents(1550),
+    };
+
+    util::test_type(&mut conn, "inventory_item", &[(item, "ROW('foobar', 1550)")]);
+}
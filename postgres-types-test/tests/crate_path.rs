@@ -0,0 +1,74 @@
+use postgres_derive::{FromSql, ToSql};
+
+mod util;
+
+#[test]
+fn tokio_postgres() {
+    #[derive(Debug, ToSql, FromSql, PartialEq)]
+    #[postgres(name = "mood", crate = "::tokio_postgres")]
+    enum Mood {
+        #[postgres(name = "sad")]
+        Sad,
+        #[postgres(name = "happy")]
+        Happy,
+    }
+
+    let mut conn = util::connect();
+    conn.execute("CREATE TYPE pg_temp.mood AS ENUM ('sad', 'happy')", &[])
+        .unwrap();
+
+    util::test_type(
+        &mut conn,
+        "mood",
+        &[(Mood::Sad, "'sad'"), (Mood::Happy, "'happy'")],
+    );
+}
+
+#[test]
+fn postgres_types() {
+    #[derive(Debug, ToSql, FromSql, PartialEq)]
+    #[postgres(name = "inventory_item", crate = "::postgres_types")]
+    struct InventoryItem {
+        name: String,
+        supplier_id: i32,
+        price: Option<f64>,
+    }
+
+    let mut conn = util::connect();
+    conn.batch_execute(
+        "CREATE TYPE pg_temp.inventory_item AS (
+                            name TEXT,
+                            supplier_id INT,
+                            price DOUBLE PRECISION
+                        );",
+    ).unwrap();
+
+    let item = InventoryItem {
+        name: "foobar".to_owned(),
+        supplier_id: 100,
+        price: Some(15.50),
+    };
+
+    util::test_type(
+        &mut conn,
+        "inventory_item",
+        &[(item, "ROW('foobar', 100, 15.50)")],
+    );
+}
+
+#[test]
+fn types() {
+    #[derive(Debug, ToSql, FromSql, PartialEq)]
+    #[postgres(name = "session_id", types = "::postgres_types")]
+    struct SessionId(Vec<u8>);
+
+    let mut conn = util::connect();
+    conn.execute("CREATE DOMAIN pg_temp.session_id AS bytea", &[])
+        .unwrap();
+
+    util::test_type(
+        &mut conn,
+        "session_id",
+        &[(SessionId(b"0123456789abcdef".to_vec()), "'0123456789abcdef'")],
+    );
+}
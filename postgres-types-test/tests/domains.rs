@@ -0,0 +1,116 @@
+use postgres_derive::{FromSql, ToSql};
+use postgres_derive_runtime::error::DecodeErrorKind;
+use postgres_derive_runtime::{DecodeError, ValidationError};
+use std::error::Error;
+
+mod util;
+
+#[test]
+fn defaults() {
+    #[derive(FromSql, ToSql, Debug, PartialEq)]
+    #[postgres(name = "session_id")]
+    struct SessionId(Vec<u8>);
+
+    let mut conn = util::connect();
+    conn.execute(
+        "CREATE DOMAIN pg_temp.session_id AS bytea CHECK(octet_length(VALUE) = 16);",
+        &[],
+    ).unwrap();
+
+    util::test_type(
+        &mut conn,
+        "session_id",
+        &[(SessionId(b"0123456789abcdef".to_vec()), "'0123456789abcdef'")],
+    );
+}
+
+#[test]
+fn conversions() {
+    #[derive(FromSql, ToSql, Debug, PartialEq, Clone)]
+    #[postgres(try_from = "String", into = "String")]
+    struct Email(String);
+
+    impl TryFrom<String> for Email {
+        type Error = String;
+
+        fn try_from(s: String) -> Result<Email, String> {
+            if s.contains('@') {
+                Ok(Email(s))
+            } else {
+                Err(format!("`{}` is not an email address", s))
+            }
+        }
+    }
+
+    impl From<Email> for String {
+        fn from(email: Email) -> String {
+            email.0
+        }
+    }
+
+    let mut conn = util::connect();
+    conn.execute("CREATE DOMAIN pg_temp.email AS TEXT;", &[]).unwrap();
+
+    util::test_type(
+        &mut conn,
+        "email",
+        &[(Email("foo@example.com".to_owned()), "'foo@example.com'")],
+    );
+
+    let row = conn.query_one("SELECT 'foo'::email", &[]).unwrap();
+    let err = row.try_get::<_, Email>(0).unwrap_err();
+    let err = err.source().unwrap().downcast_ref::<DecodeError>().unwrap();
+    assert_eq!(*err.kind(), DecodeErrorKind::InvalidValue);
+}
+
+#[test]
+fn validate() {
+    #[derive(FromSql, ToSql, Debug, PartialEq)]
+    #[postgres(name = "session_id", validate = "SessionId::validate")]
+    struct SessionId(Vec<u8>);
+
+    impl SessionId {
+        fn validate(&self) -> Result<(), String> {
+            if self.0.len() == 16 {
+                Ok(())
+            } else {
+                Err(format!("expected 16 bytes, got {}", self.0.len()))
+            }
+        }
+    }
+
+    let mut conn = util::connect();
+    conn.execute("CREATE DOMAIN pg_temp.session_id AS bytea;", &[]).unwrap();
+
+    util::test_type(
+        &mut conn,
+        "session_id",
+        &[(SessionId(b"0123456789abcdef".to_vec()), "'0123456789abcdef'")],
+    );
+
+    let err = conn.execute("SELECT $1::session_id", &[&SessionId(vec![])])
+        .unwrap_err();
+    assert!(err.source().unwrap().is::<ValidationError>());
+}
+
+#[test]
+fn domain_over_composite() {
+    #[derive(FromSql, ToSql, Debug, PartialEq)]
+    #[postgres(name = "point")]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(FromSql, ToSql, Debug, PartialEq)]
+    #[postgres(name = "origin")]
+    struct Origin(Point);
+
+    let mut conn = util::connect();
+    conn.batch_execute(
+        "CREATE TYPE pg_temp.point AS (x INT, y INT);
+         CREATE DOMAIN pg_temp.origin AS pg_temp.point;",
+    ).unwrap();
+
+    util::test_type(&mut conn, "origin", &[(Origin(Point { x: 1, y: 2 }), "ROW(1, 2)")]);
+}
@@ -0,0 +1,196 @@
+use postgres::types::WrongType;
+use postgres_derive::{FromSql, PgSet, ToSql};
+use postgres_derive_runtime::error::DecodeErrorKind;
+use postgres_derive_runtime::DecodeError;
+use std::error::Error;
+
+mod util;
+
+#[test]
+fn defaults() {
+    #[derive(Debug, ToSql, FromSql, PartialEq)]
+    #[postgres(name = "mood")]
+    enum Mood {
+        #[postgres(name = "sad")]
+        Sad,
+        #[postgres(name = "happy")]
+        Happy,
+    }
+
+    let mut conn = util::connect();
+    conn.execute("CREATE TYPE pg_temp.mood AS ENUM ('sad', 'happy')", &[])
+        .unwrap();
+
+    util::test_type(
+        &mut conn,
+        "mood",
+        &[(Mood::Sad, "'sad'"), (Mood::Happy, "'happy'")],
+    );
+
+    let err = conn.execute("SELECT $1::text", &[&Mood::Sad]).unwrap_err();
+    assert!(err.source().unwrap().is::<WrongType>());
+}
+
+#[test]
+fn discriminants() {
+    const TWO: isize = 2;
+
+    #[derive(Debug, ToSql, FromSql, PartialEq)]
+    #[postgres(name = "mood")]
+    enum Mood {
+        #[postgres(name = "sad")]
+        Sad = 1 << 0,
+        #[postgres(name = "happy")]
+        Happy = TWO,
+    }
+
+    let mut conn = util::connect();
+    conn.execute("CREATE TYPE pg_temp.mood AS ENUM ('sad', 'happy')", &[])
+        .unwrap();
+
+    util::test_type(
+        &mut conn,
+        "mood",
+        &[(Mood::Sad, "'sad'"), (Mood::Happy, "'happy'")],
+    );
+}
+
+#[test]
+fn text_repr() {
+    #[derive(Debug, ToSql, FromSql, PartialEq)]
+    #[postgres(repr = "text")]
+    enum Status {
+        #[postgres(name = "open")]
+        Open,
+        #[postgres(name = "closed")]
+        Closed,
+    }
+
+    let mut conn = util::connect();
+
+    for sql_type in &["text", "varchar", "bpchar(10)"] {
+        util::test_type(
+            &mut conn,
+            sql_type,
+            &[(Status::Open, "'open'"), (Status::Closed, "'closed'")],
+        );
+    }
+}
+
+#[test]
+fn int_repr() {
+    #[derive(Debug, ToSql, FromSql, PartialEq)]
+    #[postgres(repr = "int2")]
+    enum Status {
+        Open = 1,
+        Closed = 2,
+        Deleted = -1,
+    }
+
+    let mut conn = util::connect();
+
+    util::test_type(
+        &mut conn,
+        "int2",
+        &[(Status::Open, "1"), (Status::Closed, "2"), (Status::Deleted, "-1")],
+    );
+
+    let row = conn.query_one("SELECT 3::int2", &[]).unwrap();
+    let err = row.try_get::<_, Status>(0).unwrap_err();
+    let err = err.source().unwrap().downcast_ref::<DecodeError>().unwrap();
+    assert_eq!(*err.kind(), DecodeErrorKind::InvalidVariant("3".to_owned()));
+}
+
+#[test]
+fn char_repr() {
+    #[derive(Debug, ToSql, FromSql, PartialEq)]
+    #[postgres(repr = "char")]
+    enum Kind {
+        #[postgres(code = 'r')]
+        Table,
+        #[postgres(code = 'i')]
+        Index,
+    }
+
+    let mut conn = util::connect();
+
+    util::test_type(
+        &mut conn,
+        "\"char\"",
+        &[(Kind::Table, "'r'"), (Kind::Index, "'i'")],
+    );
+}
+
+#[test]
+fn null_variant() {
+    #[derive(Debug, ToSql, FromSql, PartialEq)]
+    #[postgres(name = "grade")]
+    enum Grade {
+        #[postgres(null)]
+        Unknown,
+        A,
+        B,
+    }
+
+    let mut conn = util::connect();
+    conn.execute("CREATE TYPE pg_temp.grade AS ENUM ('A', 'B')", &[])
+        .unwrap();
+
+    util::test_type(
+        &mut conn,
+        "grade",
+        &[(Grade::Unknown, "NULL"), (Grade::A, "'A'"), (Grade::B, "'B'")],
+    );
+}
+
+#[test]
+fn set() {
+    #[derive(Debug, ToSql, FromSql, PgSet, PartialEq)]
+    #[postgres(name = "role", set = "RoleSet")]
+    enum Role {
+        #[postgres(name = "reader")]
+        Reader,
+        #[postgres(name = "writer")]
+        Writer,
+        #[postgres(name = "admin")]
+        Admin,
+    }
+
+    let mut conn = util::connect();
+    conn.execute("CREATE TYPE pg_temp.role AS ENUM ('reader', 'writer', 'admin')", &[])
+        .unwrap();
+
+    let set = vec![Role::Reader, Role::Admin].into_iter().collect::<RoleSet>();
+
+    util::test_type(
+        &mut conn,
+        "role[]",
+        &[(RoleSet::new(), "'{}'"), (set, "'{reader,admin}'")],
+    );
+
+    let row = conn.query_one("SELECT '{writer,writer}'::role[]", &[]).unwrap();
+    let err = row.try_get::<_, RoleSet>(0).unwrap_err();
+    let err = err.source().unwrap().downcast_ref::<DecodeError>().unwrap();
+    assert_eq!(err.path(), "_role[1]");
+    assert_eq!(*err.kind(), DecodeErrorKind::DuplicateElement);
+}
+
+#[test]
+fn from_sql_set() {
+    #[derive(Debug, FromSql, PgSet, PartialEq)]
+    #[postgres(name = "role", set = "RoleSet")]
+    enum Role {
+        #[postgres(name = "reader")]
+        Reader,
+        #[postgres(name = "writer")]
+        Writer,
+    }
+
+    let mut conn = util::connect();
+    conn.execute("CREATE TYPE pg_temp.role AS ENUM ('reader', 'writer')", &[])
+        .unwrap();
+
+    let row = conn.query_one("SELECT '{writer}'::role[]", &[]).unwrap();
+    let set = row.get::<_, RoleSet>(0);
+    assert_eq!(set, vec![Role::Writer].into_iter().collect());
+}
@@ -0,0 +1,73 @@
+use postgres_derive::{FromSql, ToSql};
+
+mod util;
+
+#[test]
+fn bit() {
+    #[derive(FromSql, ToSql, Debug, PartialEq)]
+    #[postgres(flags, width = 8)]
+    struct Permissions {
+        read: bool,
+        write: bool,
+        #[postgres(bit = 7)]
+        admin: bool,
+    }
+
+    let mut conn = util::connect();
+
+    util::test_type(
+        &mut conn,
+        "bit(8)",
+        &[
+            (Permissions { read: false, write: false, admin: false }, "B'00000000'"),
+            (Permissions { read: true, write: false, admin: true }, "B'10000001'"),
+        ],
+    );
+}
+
+#[test]
+fn int() {
+    #[derive(FromSql, ToSql, Debug, PartialEq)]
+    #[postgres(flags, repr = "int4")]
+    struct Permissions {
+        read: bool,
+        write: bool,
+        #[postgres(bit = 31)]
+        admin: bool,
+    }
+
+    let mut conn = util::connect();
+
+    util::test_type(
+        &mut conn,
+        "int4",
+        &[
+            (Permissions { read: true, write: true, admin: false }, "3"),
+            (Permissions { read: false, write: false, admin: true }, "(-2147483648)"),
+        ],
+    );
+}
+
+#[test]
+fn wide() {
+    #[derive(FromSql, ToSql, Debug, PartialEq)]
+    #[postgres(flags, repr = "varbit", width = 1000000)]
+    struct Wide {
+        first: bool,
+        #[postgres(bit = 999999)]
+        last: bool,
+    }
+
+    let mut conn = util::connect();
+
+    let value = Wide { first: false, last: true };
+    let row = conn
+        .query_one(
+            "SELECT length($1::varbit), get_bit($1::varbit, 999999), $1::varbit",
+            &[&value],
+        )
+        .unwrap();
+    assert_eq!(row.get::<_, i32>(0), 1000000);
+    assert_eq!(row.get::<_, i32>(1), 1);
+    assert_eq!(row.get::<_, Wide>(2), value);
+}
@@ -1,86 +1,179 @@
-use std::iter;
-use syn::Ident;
+use syn::{Ident, Path};
 use quote::Tokens;
 
-use enums::Variant;
+use enums::{Repr, Variant};
+use flags::{self, Flags};
 use composites::Field;
 
-pub fn domain_body(name: &str, field: &syn::Field) -> Tokens {
-    let ty = &field.ty;
+pub fn domain_body(types: &Path, name: &str, field: &syn::Field) -> Tokens {
+    let base = domain_base(types, "ToSql", &field.ty, quote!(type_));
 
     quote! {
         if type_.name() != #name {
             return false;
         }
 
-        match *type_.kind() {
-            ::postgres::types::Kind::Domain(ref type_) => {
-                <#ty as ::postgres::types::ToSql>::accepts(type_)
+        #base.is_some()
+    }
+}
+
+/// Walks the domain chain from the type `type_` to the first base type accepted by `ty`, which may
+/// itself be a domain, a composite, or an enum.
+pub fn domain_base(types: &Path, trait_: &str, ty: &syn::Type, type_: Tokens) -> Tokens {
+    let trait_ = Ident::from(trait_);
+
+    quote! {
+        {
+            let mut base = #type_;
+            loop {
+                match *base.kind() {
+                    #types::Kind::Domain(ref inner) => {
+                        base = inner;
+                        if <#ty as #types::#trait_>::accepts(base) {
+                            break ::std::option::Option::Some(base);
+                        }
+                    }
+                    _ => break ::std::option::Option::None,
+                }
             }
-            _ => false,
         }
     }
 }
 
-pub fn enum_body(name: &str, variants: &[Variant]) -> Tokens {
+pub fn enum_body(types: &Path, name: &str, variants: &[Variant], ordered: bool, repr: Repr)
+                 -> Tokens {
+    match repr {
+        Repr::Enum => {}
+        Repr::Text => return text_enum_body(types),
+        Repr::Int2 | Repr::Int4 | Repr::Int8 | Repr::Char => return int_enum_body(types, repr),
+    }
+
     let num_variants = variants.len();
     let variant_names = variants.iter().map(|v| &v.name);
 
+    // Postgres reports enum labels in sort order, so ordered enums must match them exactly
+    let check = if ordered {
+        quote! {
+            variants.iter().zip([#(#variant_names),*].iter()).all(|(v, n)| &**v == *n)
+        }
+    } else {
+        quote! {
+            variants.iter().all(|v| {
+                match &**v {
+                    #(
+                        #variant_names => true,
+                    )*
+                    _ => false,
+                }
+            })
+        }
+    };
+
     quote! {
         if type_.name() != #name {
             return false;
         }
 
         match *type_.kind() {
-            ::postgres::types::Kind::Enum(ref variants) => {
+            #types::Kind::Enum(ref variants) => {
                 if variants.len() != #num_variants {
                     return false;
                 }
 
-                variants.iter().all(|v| {
-                    match &**v {
-                        #(
-                            #variant_names => true,
-                        )*
-                        _ => false,
-                    }
-                })
+                #check
             }
             _ => false,
         }
     }
 }
 
-pub fn composite_body(name: &str, trait_: &str, fields: &[Field]) -> Tokens {
+fn text_enum_body(types: &Path) -> Tokens {
+    let base = base_type(types, quote!(type_));
+
+    quote! {
+        match #base.name() {
+            "text" | "varchar" | "bpchar" | "name" | "citext" => true,
+            _ => false,
+        }
+    }
+}
+
+fn int_enum_body(types: &Path, repr: Repr) -> Tokens {
+    let ty = Ident::from(repr.rust_type().unwrap());
+    let base = base_type(types, quote!(type_));
+
+    quote! {
+        <#ty as #types::ToSql>::accepts(#base)
+    }
+}
+
+pub fn flags_body(types: &Path, flags: &Flags) -> Tokens {
+    let base = base_type(types, quote!(type_));
+
+    match flags.repr {
+        flags::Repr::Bit => quote!(#base.name() == "bit"),
+        flags::Repr::Varbit => quote!(#base.name() == "varbit"),
+        _ => {
+            let ty = flags.repr.int_type().unwrap();
+            quote!(<#ty as #types::ToSql>::accepts(#base))
+        }
+    }
+}
+
+/// Walks the full domain chain from the type `type_` to its base type.
+pub fn base_type(types: &Path, type_: Tokens) -> Tokens {
+    quote! {
+        {
+            let mut base = #type_;
+            while let #types::Kind::Domain(ref inner) = *base.kind() {
+                base = inner;
+            }
+            base
+        }
+    }
+}
+
+pub fn composite_body(types: &Path,
+                      runtime: &Path,
+                      name: &str,
+                      trait_: &str,
+                      fields: &[Field])
+                      -> Tokens {
     let num_fields = fields.len();
     let trait_ = Ident::from(trait_);
-    let traits = iter::repeat(&trait_);
-    let field_names = fields.iter().map(|f| &f.name);
-    let field_types = fields.iter().map(|f| &f.type_);
+    let checks = fields.iter().map(|f| {
+        let field_name = &f.name;
+        let field_type = &f.type_;
+        match f.with {
+            Some(ref with) => quote!(#field_name => #with::accepts(f.type_()),),
+            None => {
+                quote!(#field_name => <#field_type as #types::#trait_>::accepts(f.type_()),)
+            }
+        }
+    });
 
+    // Checking the fields recurses into every nested type, so the result is cached
     quote! {
+        static ACCEPTS: #runtime::cache::AcceptsCache = #runtime::cache::AcceptsCache::new();
+
         if type_.name() != #name {
             return false;
         }
 
-        match *type_.kind() {
-            ::postgres::types::Kind::Composite(ref fields) => {
-                if fields.len() != #num_fields {
-                    return false;
-                }
+        let fields = match *type_.kind() {
+            #types::Kind::Composite(ref fields) => fields,
+            _ => return false,
+        };
 
-                fields.iter().all(|f| {
-                    match f.name() {
-                        #(
-                            #field_names => {
-                                <#field_types as ::postgres::types::#traits>::accepts(f.type_())
-                            }
-                        )*
-                        _ => false,
-                    }
-                })
-            }
-            _ => false,
-        }
+        ACCEPTS.get_or_insert_with(type_.oid(),
+                                   fields.iter().map(|f| (f.name(), f.type_().oid())),
+                                   || {
+            fields.len() == #num_fields && fields.iter().all(|f| {
+                match f.name() {
+                    #(#checks)*
+                    _ => false,
+                }
+            })
+        })
     }
 }
@@ -1,4 +1,4 @@
-use syn::{self, Ident, Type};
+use syn::{self, Ident, Path, Type};
 
 use overrides::Overrides;
 
@@ -6,17 +6,22 @@ pub struct Field {
     pub name: String,
     pub ident: Ident,
     pub type_: Type,
+    pub sql_type: Option<String>,
+    pub with: Option<Path>,
 }
 
 impl Field {
     pub fn parse(raw: &syn::Field) -> Result<Field, String> {
         let overrides = Overrides::extract(&raw.attrs)?;
+        overrides.check(&["name", "sql_type", "with"], "composite fields")?;
 
         let ident = raw.ident.as_ref().unwrap().clone();
         Ok(Field {
             name: overrides.name.unwrap_or_else(|| ident.to_string()),
             ident,
             type_: raw.ty.clone(),
+            sql_type: overrides.sql_type,
+            with: overrides.with,
         })
     }
 }
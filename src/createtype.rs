@@ -0,0 +1,120 @@
+use syn::{self, Path, DeriveInput, Data, DataStruct, Fields};
+use quote::Tokens;
+
+use composites::Field;
+use enums::{self, Repr, Variant};
+use flags::Flags;
+use overrides::Overrides;
+
+pub fn expand_derive_createtype(input: DeriveInput) -> Result<Tokens, String> {
+    let overrides = Overrides::extract(&input.attrs)?;
+    overrides.check_container(&input.data)?;
+
+    let runtime = overrides.runtime_path();
+    let name = quote_ident(&overrides.name.unwrap_or_else(|| input.ident.to_string()));
+
+    let create_type_body = match input.data {
+        Data::Enum(ref data) => {
+            let repr = Repr::parse(overrides.repr.as_deref())?;
+            let (variants, _) = enums::parse_variants(data, repr)?;
+            enum_body(&name, &variants, repr)?
+        }
+        Data::Struct(DataStruct { fields: Fields::Named(ref fields), .. }) if overrides.flags => {
+            let flags = Flags::parse(overrides.repr.as_deref(), overrides.width, fields)?;
+            let sql = format!("CREATE DOMAIN {} AS {}", name, flags.sql_type());
+            quote!(::std::borrow::ToOwned::to_owned(#sql))
+        }
+        Data::Struct(DataStruct { fields: Fields::Unnamed(ref fields), .. }) if fields.unnamed.len() == 1 => {
+            let field = fields.unnamed.first().unwrap().into_value();
+            domain_body(&runtime, &name, field)?
+        }
+        Data::Struct(DataStruct { fields: Fields::Named(ref fields), .. }) => {
+            let fields = fields.named.iter().map(Field::parse).collect::<Result<Vec<_>, _>>()?;
+            composite_body(&runtime, &name, &fields)
+        }
+        _ => {
+            return Err("#[derive(CreateType)] may only be applied to structs, single field tuple \
+                        structs, and enums".to_owned())
+        }
+    };
+
+    let ident = &input.ident;
+    let out = quote! {
+        impl #runtime::SqlType for #ident {
+            fn sql_type() -> ::std::string::String {
+                ::std::borrow::ToOwned::to_owned(#name)
+            }
+        }
+
+        impl #runtime::CreateType for #ident {
+            fn create_type_sql() -> ::std::string::String {
+                #create_type_body
+            }
+        }
+    };
+
+    Ok(out)
+}
+
+fn enum_body(name: &str, variants: &[Variant], repr: Repr) -> Result<Tokens, String> {
+    let labels = if repr == Repr::Char {
+        repr.values(variants)?.iter().map(|&v| quote_literal(&(v as u8 as char).to_string()))
+            .collect::<Vec<_>>()
+    } else if repr.is_int() {
+        repr.values(variants)?.iter().map(|v| v.to_string()).collect::<Vec<_>>()
+    } else {
+        variants.iter().map(|v| quote_literal(&v.name)).collect::<Vec<_>>()
+    };
+    // Enums stored as other types are constrained to their labels or codes by a domain
+    let sql = match repr.sql_type() {
+        Some(sql_type) => {
+            format!("CREATE DOMAIN {} AS {} CHECK (VALUE IN ({}))",
+                    name,
+                    sql_type,
+                    labels.join(", "))
+        }
+        None => format!("CREATE TYPE {} AS ENUM ({})", name, labels.join(", ")),
+    };
+
+    Ok(quote! {
+        ::std::borrow::ToOwned::to_owned(#sql)
+    })
+}
+
+fn domain_body(runtime: &Path, name: &str, field: &syn::Field) -> Result<Tokens, String> {
+    let overrides = Overrides::extract(&field.attrs)?;
+    let sql_type = sql_type(runtime, &field.ty, overrides.sql_type.as_ref());
+
+    Ok(quote! {
+        ::std::format!("CREATE DOMAIN {} AS {}", #name, #sql_type)
+    })
+}
+
+fn composite_body(runtime: &Path, name: &str, fields: &[Field]) -> Tokens {
+    let field_names = fields.iter().map(|f| quote_ident(&f.name));
+    let sql_types = fields.iter().map(|f| sql_type(runtime, &f.type_, f.sql_type.as_ref()));
+
+    quote! {
+        let fields: &[::std::string::String] = &[
+            #(
+                ::std::format!("{} {}", #field_names, #sql_types),
+            )*
+        ];
+        ::std::format!("CREATE TYPE {} AS ({})", #name, fields.join(", "))
+    }
+}
+
+fn sql_type(runtime: &Path, ty: &syn::Type, sql_type: Option<&String>) -> Tokens {
+    match sql_type {
+        Some(sql_type) => quote!(#sql_type),
+        None => quote!(<#ty as #runtime::SqlType>::sql_type()),
+    }
+}
+
+fn quote_ident(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "\"\""))
+}
+
+fn quote_literal(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
@@ -1,10 +1,134 @@
-use syn::{self, Ident, Fields};
+use syn::{self, DataEnum, Expr, ExprLit, ExprUnary, Ident, Fields, Lit, UnOp};
+use quote::Tokens;
+use std::collections::HashSet;
 
 use overrides::Overrides;
 
+/// The Postgres type an enum is stored as.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Repr {
+    /// A Postgres enum type.
+    Enum,
+    /// A text type, like `text CHECK (VALUE IN (...))` columns.
+    Text,
+    /// An `int2` code.
+    Int2,
+    /// An `int4` code.
+    Int4,
+    /// An `int8` code.
+    Int8,
+    /// A single byte `"char"` code.
+    Char,
+}
+
+impl Repr {
+    pub fn parse(repr: Option<&str>) -> Result<Repr, String> {
+        match repr {
+            None | Some("enum") => Ok(Repr::Enum),
+            Some("text") => Ok(Repr::Text),
+            Some("int2") => Ok(Repr::Int2),
+            Some("int4") => Ok(Repr::Int4),
+            Some("int8") => Ok(Repr::Int8),
+            Some("char") => Ok(Repr::Char),
+            Some(repr) => Err(format!("unknown enum repr `{}`", repr)),
+        }
+    }
+
+    /// Returns the Postgres type of non-enum representations, as used in a `CREATE DOMAIN`.
+    pub fn sql_type(&self) -> Option<&'static str> {
+        match *self {
+            Repr::Enum => None,
+            Repr::Text => Some("TEXT"),
+            Repr::Int2 => Some("INT2"),
+            Repr::Int4 => Some("INT4"),
+            Repr::Int8 => Some("INT8"),
+            Repr::Char => Some("\"char\""),
+        }
+    }
+
+    /// Returns the Rust type values are encoded as for non-enum representations.
+    pub fn rust_type(&self) -> Option<&'static str> {
+        match *self {
+            Repr::Enum => None,
+            Repr::Text => Some("String"),
+            Repr::Int2 => Some("i16"),
+            Repr::Int4 => Some("i32"),
+            Repr::Int8 => Some("i64"),
+            Repr::Char => Some("i8"),
+        }
+    }
+
+    pub fn is_int(&self) -> bool {
+        match *self {
+            Repr::Int2 | Repr::Int4 | Repr::Int8 | Repr::Char => true,
+            Repr::Enum | Repr::Text => false,
+        }
+    }
+
+    /// Returns the code of each variant of an integer representation, checking that they are
+    /// present, distinct, and in range.
+    pub fn values(&self, variants: &[Variant]) -> Result<Vec<i64>, String> {
+        let (min, max) = match *self {
+            Repr::Int2 => (i64::from(i16::MIN), i64::from(i16::MAX)),
+            Repr::Int4 => (i64::from(i32::MIN), i64::from(i32::MAX)),
+            _ => (i64::MIN, i64::MAX),
+        };
+
+        if *self == Repr::Char {
+            return codes(variants);
+        }
+
+        let mut seen = HashSet::new();
+        variants.iter().map(|v| {
+            let value = match (v.value, v.discriminant.as_ref()) {
+                (Some(value), _) => value,
+                (None, Some(expr)) => discriminant(expr).ok_or_else(|| {
+                    format!("the discriminant of variant `{}` must be an integer literal",
+                            v.ident)
+                })?,
+                (None, None) => {
+                    return Err(format!("variant `{}` needs a discriminant or a \
+                                        #[postgres(value = ...)]", v.ident))
+                }
+            };
+            if value < min || value > max {
+                return Err(format!("value {} of variant `{}` is out of range", value, v.ident));
+            }
+            if !seen.insert(value) {
+                return Err(format!("duplicate value {} of variant `{}`", value, v.ident));
+            }
+            Ok(value)
+        }).collect()
+    }
+
+    /// Returns a literal of the Rust type of an integer representation.
+    pub fn literal(&self, value: i64) -> Tokens {
+        match *self {
+            Repr::Int2 => {
+                let value = value as i16;
+                quote!(#value)
+            }
+            Repr::Int4 => {
+                let value = value as i32;
+                quote!(#value)
+            }
+            Repr::Char => {
+                let value = value as i8;
+                quote!(#value)
+            }
+            _ => quote!(#value),
+        }
+    }
+}
+
 pub struct Variant {
     pub ident: Ident,
     pub name: String,
+    pub value: Option<i64>,
+    /// The discriminant expression, which is only evaluated by integer representations.
+    pub discriminant: Option<Expr>,
+    pub code: Option<char>,
+    pub null: bool,
 }
 
 impl Variant {
@@ -15,9 +139,92 @@ impl Variant {
         }
 
         let overrides = Overrides::extract(&raw.attrs)?;
+        overrides.check(&["name", "value", "code", "null"], "enum variants")?;
+
         Ok(Variant {
             ident: raw.ident.clone(),
             name: overrides.name.unwrap_or_else(|| raw.ident.to_string()),
+            value: overrides.value,
+            discriminant: raw.discriminant.as_ref().map(|(_, expr)| expr.clone()),
+            code: overrides.code,
+            null: overrides.null,
         })
     }
 }
+
+/// Checks that an enum marked `ordered` sorts the same way in Rust and Postgres.
+///
+/// Only Postgres enums have a declared label order, and SQL sorts `NULL` after every other value
+/// wherever its variant is declared. A derived `Ord` compares discriminants rather than
+/// declaration order, so explicit discriminants could reorder the Rust variants.
+pub fn check_ordered(ordered: bool, repr: Repr, variants: &[Variant], null: &Option<Variant>)
+                     -> Result<(), String> {
+    if !ordered {
+        return Ok(());
+    }
+    if repr != Repr::Enum {
+        return Err("`ordered` may only be applied to enums with repr = \"enum\"".to_owned());
+    }
+    if null.is_some() {
+        return Err("`ordered` may not be combined with a #[postgres(null)] variant".to_owned());
+    }
+    if let Some(v) = variants.iter().find(|v| v.discriminant.is_some()) {
+        return Err(format!("`ordered` may not be combined with the discriminant of variant `{}`",
+                           v.ident));
+    }
+    Ok(())
+}
+
+/// Parses the variants of an enum, separating out the variant stored as `NULL`, if any.
+pub fn parse_variants(data: &DataEnum, repr: Repr)
+                      -> Result<(Vec<Variant>, Option<Variant>), String> {
+    let mut variants = vec![];
+    let mut null = None;
+    for variant in &data.variants {
+        let variant = Variant::parse(variant)?;
+        if variant.value.is_some() && (variant.null || !repr.is_int() || repr == Repr::Char) {
+            return Err(format!("`value` may only be applied to non-null variants of enums with \
+                                an integer repr, not `{}`", variant.ident));
+        }
+        if variant.code.is_some() && (variant.null || repr != Repr::Char) {
+            return Err(format!("`code` may only be applied to non-null variants of enums with \
+                                repr = \"char\", not `{}`", variant.ident));
+        }
+        if !variant.null {
+            variants.push(variant);
+        } else if null.is_none() {
+            null = Some(variant);
+        } else {
+            return Err("only one variant may be #[postgres(null)]".to_owned());
+        }
+    }
+
+    Ok((variants, null))
+}
+
+// The codes of a `"char"` representation, which are ASCII and so always fit in an `i8`.
+fn codes(variants: &[Variant]) -> Result<Vec<i64>, String> {
+    let mut seen = HashSet::new();
+    variants.iter().map(|v| {
+        let code = match v.code {
+            Some(code) => code,
+            None => return Err(format!("variant `{}` needs a #[postgres(code = ...)]", v.ident)),
+        };
+        if !seen.insert(code) {
+            return Err(format!("duplicate code {:?} of variant `{}`", code, v.ident));
+        }
+        Ok(code as i64)
+    }).collect()
+}
+
+fn discriminant(expr: &Expr) -> Option<i64> {
+    match *expr {
+        Expr::Lit(ExprLit { lit: Lit::Int(ref lit), .. }) if lit.value() <= i64::MAX as u64 => {
+            Some(lit.value() as i64)
+        }
+        Expr::Unary(ExprUnary { op: UnOp::Neg(_), ref expr, .. }) => {
+            discriminant(expr).map(|value| -value)
+        }
+        _ => None,
+    }
+}
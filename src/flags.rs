@@ -0,0 +1,153 @@
+use std::collections::HashSet;
+use syn::{FieldsNamed, Ident};
+use quote::Tokens;
+
+use overrides::Overrides;
+
+// Postgres limits bit strings to 10MB.
+const MAX_BIT_STRING_WIDTH: usize = 10 * 1024 * 1024 * 8;
+
+/// The Postgres type a set of flags is stored as.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Repr {
+    /// A `bit(n)` string.
+    Bit,
+    /// A `varbit` string.
+    Varbit,
+    /// An `int2` mask.
+    Int2,
+    /// An `int4` mask.
+    Int4,
+    /// An `int8` mask.
+    Int8,
+}
+
+impl Repr {
+    pub fn parse(repr: Option<&str>) -> Result<Repr, String> {
+        match repr {
+            None | Some("bit") => Ok(Repr::Bit),
+            Some("varbit") => Ok(Repr::Varbit),
+            Some("int2") => Ok(Repr::Int2),
+            Some("int4") => Ok(Repr::Int4),
+            Some("int8") => Ok(Repr::Int8),
+            Some(repr) => Err(format!("unknown flags repr `{}`", repr)),
+        }
+    }
+
+    /// Returns the Rust type of integer masks.
+    pub fn int_type(&self) -> Option<Ident> {
+        match *self {
+            Repr::Bit | Repr::Varbit => None,
+            Repr::Int2 => Some(Ident::from("i16")),
+            Repr::Int4 => Some(Ident::from("i32")),
+            Repr::Int8 => Some(Ident::from("i64")),
+        }
+    }
+
+    /// Returns the mask of a bit of an integer representation, as a literal of its Rust type.
+    pub fn mask(&self, bit: usize) -> Tokens {
+        let mask = 1u64 << bit;
+        match *self {
+            Repr::Int2 => {
+                let mask = mask as i16;
+                quote!(#mask)
+            }
+            Repr::Int4 => {
+                let mask = mask as i32;
+                quote!(#mask)
+            }
+            _ => {
+                let mask = mask as i64;
+                quote!(#mask)
+            }
+        }
+    }
+
+    fn max_bits(&self) -> usize {
+        match *self {
+            Repr::Bit | Repr::Varbit => MAX_BIT_STRING_WIDTH,
+            Repr::Int2 => 16,
+            Repr::Int4 => 32,
+            Repr::Int8 => 64,
+        }
+    }
+}
+
+pub struct Flag {
+    pub ident: Ident,
+    /// The position of the flag, counting from the left of bit strings or the least significant
+    /// bit of integers.
+    pub bit: usize,
+}
+
+pub struct Flags {
+    pub repr: Repr,
+    /// The length of bit strings.
+    pub width: usize,
+    pub flags: Vec<Flag>,
+}
+
+impl Flags {
+    pub fn parse(repr: Option<&str>, width: Option<i64>, fields: &FieldsNamed)
+                 -> Result<Flags, String> {
+        let repr = Repr::parse(repr)?;
+        if width.is_some() && repr.int_type().is_some() {
+            return Err("`width` may only be applied to flags with repr = \"bit\" or \"varbit\""
+                .to_owned());
+        }
+
+        let mut bits = HashSet::new();
+        let mut flags = vec![];
+        for (i, field) in fields.named.iter().enumerate() {
+            let ident = field.ident.unwrap();
+            let ty = &field.ty;
+            if quote!(#ty).to_string() != "bool" {
+                return Err(format!("flag `{}` must be a `bool`", ident));
+            }
+
+            let overrides = Overrides::extract(&field.attrs)?;
+            overrides.check(&["bit"], "flags")?;
+            let bit = match overrides.bit {
+                Some(bit) if bit < 0 => return Err(format!("invalid bit {} of `{}`", bit, ident)),
+                Some(bit) => bit as usize,
+                None => i,
+            };
+            if bit >= repr.max_bits() {
+                return Err(format!("bit {} of `{}` is out of range", bit, ident));
+            }
+            if !bits.insert(bit) {
+                return Err(format!("duplicate bit {} of `{}`", bit, ident));
+            }
+
+            flags.push(Flag { ident, bit });
+        }
+
+        let min_width = flags.iter().map(|f| f.bit + 1).max().unwrap_or(0);
+        let width = match width {
+            Some(width) if width > MAX_BIT_STRING_WIDTH as i64 => {
+                return Err(format!("width {} is out of range", width));
+            }
+            Some(width) if width < min_width as i64 => {
+                return Err(format!("width {} can't hold bit {}", width, min_width - 1));
+            }
+            Some(width) => width as usize,
+            None => min_width,
+        };
+        if repr == Repr::Bit && width == 0 {
+            return Err("bit strings must have a width of at least 1".to_owned());
+        }
+
+        Ok(Flags { repr, width, flags })
+    }
+
+    /// Returns the Postgres type of the flags, as it would appear in a column definition.
+    pub fn sql_type(&self) -> String {
+        match self.repr {
+            Repr::Bit => format!("BIT({})", self.width),
+            Repr::Varbit => "VARBIT".to_owned(),
+            Repr::Int2 => "INT2".to_owned(),
+            Repr::Int4 => "INT4".to_owned(),
+            Repr::Int8 => "INT8".to_owned(),
+        }
+    }
+}
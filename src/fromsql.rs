@@ -1,30 +1,64 @@
 use std::iter;
-use syn::{self, Ident, DeriveInput, Data, DataStruct, Fields};
+use syn::{self, Ident, Path, DeriveInput, Data, DataStruct, Fields};
 use quote::Tokens;
 
 use accepts;
 use composites::Field;
-use enums::Variant;
+use enums::{self, Repr, Variant};
+use flags::Flags;
 use overrides::Overrides;
+use traits;
 
 pub fn expand_derive_fromsql(input: DeriveInput) -> Result<Tokens, String> {
     let overrides = Overrides::extract(&input.attrs)?;
+    overrides.check_container(&input.data)?;
+
+    let types = overrides.types_path();
+    let runtime = overrides.runtime_path();
+
+    if let Some(ref from) = overrides.from {
+        return Ok(conversion_impl(&types, &runtime, &input.ident, from, false));
+    }
+    if let Some(ref try_from) = overrides.try_from {
+        return Ok(conversion_impl(&types, &runtime, &input.ident, try_from, true));
+    }
 
     let name = overrides.name.unwrap_or_else(|| input.ident.to_string());
 
+    let mut from_sql_null = None;
+    let mut set_tokens = None;
     let (accepts_body, to_sql_body) = match input.data {
         Data::Enum(ref data) => {
-            let variants = data.variants.iter().map(Variant::parse).collect::<Result<Vec<_>, _>>()?;
-            (accepts::enum_body(&name, &variants), enum_body(&input.ident, &variants))
+            let repr = Repr::parse(overrides.repr.as_deref())?;
+            let (variants, null) = enums::parse_variants(data, repr)?;
+            enums::check_ordered(overrides.ordered, repr, &variants, &null)?;
+            if let Some(ref set) = overrides.set {
+                if null.is_some() {
+                    return Err("sets may not contain a #[postgres(null)] variant".to_owned());
+                }
+                set_tokens = Some(set_impl(&types, &runtime, set, &input.ident));
+            }
+            from_sql_null = null.map(|v| null_body(&types, &input.ident, &v));
+            (accepts::enum_body(&types, &name, &variants, overrides.ordered, repr),
+             if repr.is_int() {
+                 int_enum_body(&types, &runtime, &input.ident, &variants, repr)?
+             } else {
+                 enum_body(&types, &runtime, &input.ident, &variants, repr)
+             })
+        }
+        Data::Struct(DataStruct { fields: Fields::Named(ref fields), .. }) if overrides.flags => {
+            let flags = Flags::parse(overrides.repr.as_deref(), overrides.width, fields)?;
+            (accepts::flags_body(&types, &flags),
+             flags_body(&types, &runtime, &input.ident, &flags))
         }
         Data::Struct(DataStruct { fields: Fields::Unnamed(ref fields), .. }) if fields.unnamed.len() == 1 => {
             let field = fields.unnamed.first().unwrap().into_value();
-            (domain_accepts_body(&name, field), domain_body(&input.ident, field))
+            (domain_accepts_body(&types, &name, field), domain_body(&types, &input.ident, field))
         }
         Data::Struct(DataStruct { fields: Fields::Named(ref fields), .. }) => {
             let fields = fields.named.iter().map(Field::parse).collect::<Result<Vec<_>, _>>()?;
-            (accepts::composite_body(&name, "FromSql", &fields),
-             composite_body(&input.ident, &fields))
+            (accepts::composite_body(&types, &runtime, &name, "FromSql", &fields),
+             composite_body(&types, &runtime, &input.ident, &fields))
         }
         _ => {
             return Err("#[derive(ToSql)] may only be applied to structs, single field tuple \
@@ -33,142 +67,384 @@ pub fn expand_derive_fromsql(input: DeriveInput) -> Result<Tokens, String> {
     };
 
     let ident = &input.ident;
+    let from_sql_body = match overrides.validate {
+        Some(ref validate) => {
+            quote! {
+                let value: #ident = { #to_sql_body }?;
+                #runtime::ValidationError::__check(_type.name(), #validate(&value))?;
+                ::std::result::Result::Ok(value)
+            }
+        }
+        None => to_sql_body,
+    };
+
+    let impl_ = traits::from_sql_impl(&types, ident);
+    let buf = traits::from_sql_buf();
+    let error = traits::error();
     let out = quote! {
-        impl ::postgres::types::FromSql for #ident {
-            fn from_sql(_type: &::postgres::types::Type,
-                        buf: &[u8])
-                        -> ::std::result::Result<#ident,
-                                                 ::std::boxed::Box<::std::error::Error +
-                                                                   ::std::marker::Sync +
-                                                                   ::std::marker::Send>> {
-                #to_sql_body
+        #impl_ {
+            fn from_sql(_type: &#types::Type,
+                        buf: #buf)
+                        -> ::std::result::Result<#ident, #error> {
+                #from_sql_body
             }
 
-            fn accepts(type_: &::postgres::types::Type) -> bool {
+            #from_sql_null
+
+            fn accepts(type_: &#types::Type) -> bool {
                 #accepts_body
             }
         }
+
+        #set_tokens
     };
 
     Ok(out)
 }
 
-fn enum_body(ident: &Ident, variants: &[Variant]) -> Tokens {
-    let variant_names = variants.iter().map(|v| &v.name);
+// Decodes the value as `from` and converts it with `From` or `TryFrom`. `NULL`s are passed through
+// so `from` may be an `Option`, and domains over a type `from` accepts are decoded as that type.
+fn conversion_impl(types: &Path, runtime: &Path, ident: &Ident, from: &syn::Type, try_: bool)
+                   -> Tokens {
+    let from_sql_base = accepts::domain_base(types, "FromSql", from, quote!(_type));
+    let accepts_base = accepts::domain_base(types, "FromSql", from, quote!(type_));
+    let convert = if try_ {
+        quote! {
+            ::std::convert::TryFrom::try_from(value)
+                .map_err(|e| #runtime::DecodeError::__invalid_value(_type.name(), e))
+        }
+    } else {
+        quote!(::std::result::Result::Ok(::std::convert::From::from(value)))
+    };
+
+    let impl_ = traits::from_sql_impl(types, ident);
+    let buf = traits::from_sql_buf();
+    let error = traits::error();
+    quote! {
+        #impl_ {
+            fn from_sql(_type: &#types::Type,
+                        buf: #buf)
+                        -> ::std::result::Result<#ident, #error> {
+                let type_ = if <#from as #types::FromSql>::accepts(_type) {
+                    _type
+                } else {
+                    #from_sql_base.unwrap_or(_type)
+                };
+                let value = <#from as #types::FromSql>::from_sql(type_, buf)?;
+                #convert
+            }
+
+            fn from_sql_null(_type: &#types::Type)
+                             -> ::std::result::Result<#ident, #error> {
+                let type_ = if <#from as #types::FromSql>::accepts(_type) {
+                    _type
+                } else {
+                    #from_sql_base.unwrap_or(_type)
+                };
+                let value = <#from as #types::FromSql>::from_sql_null(type_)?;
+                #convert
+            }
+
+            fn accepts(type_: &#types::Type) -> bool {
+                <#from as #types::FromSql>::accepts(type_) || #accepts_base.is_some()
+            }
+        }
+    }
+}
+
+fn enum_body(types: &Path, runtime: &Path, ident: &Ident, variants: &[Variant], repr: Repr)
+             -> Tokens {
+    // Matching the raw bytes lets the compiler dispatch on the length and then on each byte, and
+    // avoids validating the UTF-8 of known labels
+    let labels = variants.iter().map(|v| {
+        let bytes = v.name.bytes();
+        quote!(&[#(#bytes),*])
+    });
     let idents = iter::repeat(ident);
     let variant_idents = variants.iter().map(|v| &v.ident);
 
+    // bpchar values are padded with spaces to the length of the column
+    let trim = if repr == Repr::Text {
+        let base = accepts::base_type(types, quote!(_type));
+        quote! {
+            let buf = if #base.name() == "bpchar" {
+                let len = buf.iter().rposition(|&b| b != b' ').map_or(0, |i| i + 1);
+                &buf[..len]
+            } else {
+                buf
+            };
+        }
+    } else {
+        quote!()
+    };
+
     quote! {
-        match ::std::str::from_utf8(buf)? {
+        #trim
+
+        match buf {
             #(
-                #variant_names => ::std::result::Result::Ok(#idents::#variant_idents),
+                #labels => ::std::result::Result::Ok(#idents::#variant_idents),
             )*
-            s => {
+            buf => {
                 ::std::result::Result::Err(
-                    ::std::convert::Into::into(format!("invalid variant `{}`", s)))
+                    #runtime::DecodeError::__invalid_variant(_type.name(), buf))
+            }
+        }
+    }
+}
+
+// The set type itself is defined by the PgSet derive.
+fn set_impl(types: &Path, runtime: &Path, set: &Ident, ident: &Ident) -> Tokens {
+    let impl_ = traits::from_sql_impl(types, set);
+    let buf = traits::from_sql_buf();
+    let error = traits::error();
+
+    quote! {
+        #impl_ {
+            fn from_sql(_type: &#types::Type,
+                        buf: #buf)
+                        -> ::std::result::Result<#set, #error> {
+                let values = <::std::vec::Vec<::std::option::Option<#ident>>
+                              as #types::FromSql>::from_sql(_type, buf)?;
+                let member = match *_type.kind() {
+                    #types::Kind::Array(ref member) => member.name(),
+                    _ => _type.name(),
+                };
+
+                let mut set = #set::new();
+                for (index, value) in values.into_iter().enumerate() {
+                    let kind = match value {
+                        ::std::option::Option::Some(value) => {
+                            if set.insert(value) {
+                                continue;
+                            }
+                            #runtime::error::DecodeErrorKind::DuplicateElement
+                        }
+                        ::std::option::Option::None => {
+                            #runtime::error::DecodeErrorKind::UnexpectedNull
+                        }
+                    };
+                    return ::std::result::Result::Err(
+                        #runtime::DecodeError::__element(_type.name(), member, index, kind));
+                }
+
+                ::std::result::Result::Ok(set)
+            }
+
+            fn accepts(type_: &#types::Type) -> bool {
+                <::std::vec::Vec<::std::option::Option<#ident>>
+                 as #types::FromSql>::accepts(type_)
+            }
+        }
+    }
+}
+
+fn null_body(types: &Path, ident: &Ident, variant: &Variant) -> Tokens {
+    let variant_ident = &variant.ident;
+    let error = traits::error();
+
+    quote! {
+        fn from_sql_null(_: &#types::Type) -> ::std::result::Result<#ident, #error> {
+            ::std::result::Result::Ok(#ident::#variant_ident)
+        }
+    }
+}
+
+fn int_enum_body(types: &Path,
+                 runtime: &Path,
+                 ident: &Ident,
+                 variants: &[Variant],
+                 repr: Repr)
+                 -> Result<Tokens, String> {
+    let ty = Ident::from(repr.rust_type().unwrap());
+    let base = accepts::base_type(types, quote!(_type));
+    let arms = repr.values(variants)?.into_iter().zip(variants).map(|(value, v)| {
+        let variant_ident = &v.ident;
+        let value = repr.literal(value);
+        quote!(#value => ::std::result::Result::Ok(#ident::#variant_ident),)
+    });
+
+    // `"char"` codes are reported as the byte itself rather than its value
+    let fallback = if repr == Repr::Char {
+        quote! {
+            _ => ::std::result::Result::Err(#runtime::DecodeError::__invalid_variant(_type.name(), buf)),
+        }
+    } else {
+        quote! {
+            value => {
+                ::std::result::Result::Err(
+                    #runtime::DecodeError::__invalid_code(
+                        _type.name(), <i64 as ::std::convert::From<#ty>>::from(value), buf))
+            }
+        }
+    };
+
+    Ok(quote! {
+        match <#ty as #types::FromSql>::from_sql(#base, buf)? {
+            #(#arms)*
+            #fallback
+        }
+    })
+}
+
+fn flags_body(types: &Path, runtime: &Path, ident: &Ident, flags: &Flags) -> Tokens {
+    let idents = flags.flags.iter().map(|f| &f.ident);
+
+    // Bits without a flag are ignored
+    match flags.repr.int_type() {
+        Some(ty) => {
+            let base = accepts::base_type(types, quote!(_type));
+            let masks = flags.flags.iter().map(|f| flags.repr.mask(f.bit));
+
+            quote! {
+                let value = <#ty as #types::FromSql>::from_sql(#base, buf)?;
+                ::std::result::Result::Ok(#ident {
+                    #(
+                        #idents: value & #masks != 0,
+                    )*
+                })
+            }
+        }
+        None => {
+            let bits = flags.flags.iter().map(|f| f.bit);
+
+            quote! {
+                let bits = #runtime::bits::BitString::read(buf, _type.name())?;
+                ::std::result::Result::Ok(#ident {
+                    #(
+                        #idents: bits.get(#bits),
+                    )*
+                })
             }
         }
     }
 }
 
 // Domains are sometimes but not always just represented by the bare type (!?)
-fn domain_accepts_body(name: &str, field: &syn::Field) -> Tokens {
+fn domain_accepts_body(types: &Path, name: &str, field: &syn::Field) -> Tokens {
     let ty = &field.ty;
-    let normal_body = accepts::domain_body(name, field);
+    let base = accepts::domain_base(types, "FromSql", ty, quote!(type_));
 
     quote! {
-        if <#ty as ::postgres::types::FromSql>::accepts(type_) {
+        if <#ty as #types::FromSql>::accepts(type_) {
             return true;
         }
 
-        #normal_body
+        type_.name() == #name && #base.is_some()
     }
 }
 
-fn domain_body(ident: &Ident, field: &syn::Field) -> Tokens {
+fn domain_body(types: &Path, ident: &Ident, field: &syn::Field) -> Tokens {
     let ty = &field.ty;
+    let base = accepts::domain_base(types, "FromSql", ty, quote!(_type));
+
     quote! {
-        <#ty as ::postgres::types::FromSql>::from_sql(_type, buf).map(#ident)
+        let type_ = if <#ty as #types::FromSql>::accepts(_type) {
+            _type
+        } else {
+            #base.unwrap_or(_type)
+        };
+
+        <#ty as #types::FromSql>::from_sql(type_, buf).map(#ident)
     }
 }
 
-fn composite_body(ident: &Ident, fields: &[Field]) -> Tokens {
+fn composite_body(types: &Path, runtime: &Path, ident: &Ident, fields: &[Field]) -> Tokens {
     let temp_vars = &fields.iter().map(|f| Ident::from(format!("__{}", f.ident))).collect::<Vec<_>>();
-    let field_names = &fields.iter().map(|f| &f.name).collect::<Vec<_>>();
-    let field_idents = &fields.iter().map(|f| &f.ident).collect::<Vec<_>>();
-
-    quote! {
-        fn read_be_i32(buf: &mut &[u8]) -> ::std::io::Result<i32> {
-            let mut bytes = [0; 4];
-            ::std::io::Read::read_exact(buf, &mut bytes)?;
-            let num = ((bytes[0] as i32) << 24) |
-                ((bytes[1] as i32) << 16) |
-                ((bytes[2] as i32) << 8) |
-                (bytes[3] as i32);
-            ::std::result::Result::Ok(num)
-        }
-
-        fn read_value<T>(type_: &::postgres::types::Type,
-                         buf: &mut &[u8])
-                         -> ::std::result::Result<T,
-                             ::std::boxed::Box<::std::error::Error +
-                             ::std::marker::Sync +
-                             ::std::marker::Send>>
-                         where T: ::postgres::types::FromSql
-        {
-            let len = read_be_i32(buf)?;
-            let value = if len < 0 {
-                ::std::option::Option::None
-            } else {
-                if len as usize > buf.len() {
+    let field_names = fields.iter().map(|f| &f.name);
+    let fields_ = fields.iter().zip(temp_vars).map(|(f, temp_var)| {
+        let field_ident = &f.ident;
+        let field_name = &f.name;
+        quote! {
+            #field_ident: match #temp_var {
+                ::std::option::Option::Some(value) => value,
+                ::std::option::Option::None => {
+                    let kind = #runtime::error::DecodeErrorKind::MissingField(
+                        ::std::borrow::ToOwned::to_owned(#field_name));
                     return ::std::result::Result::Err(
-                        ::std::convert::Into::into("invalid buffer size"));
+                        #runtime::DecodeError::new(_type.name(), kind));
                 }
-                let (head, tail) = buf.split_at(len as usize);
-                *buf = tail;
-                ::std::option::Option::Some(&head[..])
-            };
-            ::postgres::types::FromSql::from_sql_nullable(type_, value)
+            },
+        }
+    });
+    let reads = fields.iter().zip(temp_vars).enumerate().map(|(position, (f, temp_var))| {
+        let from_sql = match f.with {
+            Some(ref with) => {
+                quote! {
+                    match value {
+                        ::std::option::Option::Some(value) => #with::from_sql(field.type_(), value),
+                        ::std::option::Option::None => {
+                            ::std::result::Result::Err(
+                                ::std::convert::Into::into(#types::WasNull))
+                        }
+                    }
+                }
+            }
+            None => quote!(#types::FromSql::from_sql_nullable(field.type_(), value)),
+        };
+        quote! {
+            #position => {
+                #temp_var = ::std::option::Option::Some(#from_sql.map_err(&field_error)?);
+            }
         }
+    });
 
+    let header = quote! {
         let fields = match *_type.kind() {
-            ::postgres::types::Kind::Composite(ref fields) => fields,
-            _ => unreachable!(),
+            #types::Kind::Composite(ref fields) => fields,
+            _ => {
+                let kind = #runtime::error::DecodeErrorKind::NotComposite;
+                return ::std::result::Result::Err(#runtime::DecodeError::new(_type.name(), kind));
+            }
         };
 
-        let mut buf = buf;
-        let num_fields = read_be_i32(&mut buf)?;
-        if num_fields as usize != fields.len() {
-            return ::std::result::Result::Err(
-                ::std::convert::Into::into(format!("invalid field count: {} vs {}", num_fields,
-                                                   fields.len())));
-        }
+        #runtime::record::read_header(&mut buf, _type.name(), fields.len())?;
+    };
 
-        #(
-            let mut #temp_vars = ::std::option::Option::None;
-        )*
+    quote! {
+        static PLANS: #runtime::record::PlanCache = #runtime::record::PlanCache::new();
 
-        for field in fields {
-            let oid = read_be_i32(&mut buf)? as u32;
-            if oid != field.type_().oid() {
-                return ::std::result::Result::Err(::std::convert::Into::into("unexpected OID"));
-            }
+        #runtime::DecodeError::__decode(buf, |mut buf| {
+            #header
 
-            match field.name() {
-                #(
-                    #field_names => {
-                        #temp_vars = ::std::option::Option::Some(
-                            read_value(field.type_(), &mut buf)?);
-                    }
-                )*
-                _ => unreachable!(),
-            }
-        }
+            let plan = PLANS.decode_plan(_type.name(),
+                                         _type.oid(),
+                                         &[#(#field_names),*],
+                                         fields.iter().map(|f| f.name()))?;
 
-        ::std::result::Result::Ok(#ident {
             #(
-                #field_idents: #temp_vars.unwrap(),
+                let mut #temp_vars = ::std::option::Option::None;
             )*
+
+            for (field, &position) in fields.iter().zip(plan.iter()) {
+                let value = #runtime::record::read_field(&mut buf,
+                                                         _type.name(),
+                                                         field.name(),
+                                                         field.type_().oid())?;
+                let field_error = |e| {
+                    #runtime::DecodeError::__field(_type.name(),
+                                                   field.name(),
+                                                   field.type_().name(),
+                                                   value,
+                                                   e)
+                };
+
+                match position {
+                    #(#reads)*
+                    _ => {
+                        let kind = #runtime::error::DecodeErrorKind::UnexpectedField(
+                            ::std::borrow::ToOwned::to_owned(field.name()));
+                        return ::std::result::Result::Err(
+                            #runtime::DecodeError::new(_type.name(), kind));
+                    }
+                }
+            }
+
+            #runtime::record::read_end(buf, _type.name())?;
+
+            ::std::result::Result::Ok(#ident {
+                #(#fields_)*
+            })
         })
     }
 }
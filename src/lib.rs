@@ -1,27 +1,60 @@
-#![recursion_limit = "256"]
-
 extern crate proc_macro;
 extern crate syn;
 #[macro_use]
 extern crate quote;
 
 use proc_macro::TokenStream;
+use quote::Tokens;
+use syn::DeriveInput;
 
 mod accepts;
 mod composites;
+mod createtype;
 mod enums;
+mod flags;
 mod fromsql;
 mod overrides;
+mod sets;
+mod toparams;
 mod tosql;
+mod traits;
+mod typeinfo;
 
 #[proc_macro_derive(ToSql, attributes(postgres))]
 pub fn derive_tosql(input: TokenStream) -> TokenStream {
-    let input = syn::parse(input).unwrap();
-    tosql::expand_derive_tosql(input).unwrap().into()
+    expand(input, tosql::expand_derive_tosql)
 }
 
 #[proc_macro_derive(FromSql, attributes(postgres))]
 pub fn derive_fromsql(input: TokenStream) -> TokenStream {
-    let input = syn::parse(input).unwrap();
-    fromsql::expand_derive_fromsql(input).unwrap().into()
+    expand(input, fromsql::expand_derive_fromsql)
+}
+
+#[proc_macro_derive(PgSet, attributes(postgres))]
+pub fn derive_set(input: TokenStream) -> TokenStream {
+    expand(input, sets::expand_derive_set)
+}
+
+#[proc_macro_derive(ToParams, attributes(postgres))]
+pub fn derive_toparams(input: TokenStream) -> TokenStream {
+    expand(input, toparams::expand_derive_toparams)
+}
+
+#[proc_macro_derive(CreateType, attributes(postgres))]
+pub fn derive_createtype(input: TokenStream) -> TokenStream {
+    expand(input, createtype::expand_derive_createtype)
+}
+
+#[proc_macro_derive(PgTypeInfo, attributes(postgres))]
+pub fn derive_typeinfo(input: TokenStream) -> TokenStream {
+    expand(input, typeinfo::expand_derive_typeinfo)
+}
+
+// Reports errors as a `compile_error!` in place of the generated code.
+fn expand(input: TokenStream, f: fn(DeriveInput) -> Result<Tokens, String>) -> TokenStream {
+    let result = syn::parse(input).map_err(|e| e.to_string()).and_then(f);
+    match result {
+        Ok(tokens) => tokens.into(),
+        Err(message) => quote!(::std::compile_error!(#message);).into(),
+    }
 }
@@ -1,13 +1,52 @@
-use syn::{Attribute, Meta, NestedMeta, Lit};
+use syn::{self, Attribute, Data, DataStruct, Fields, Ident, Meta, NestedMeta, Lit, Path, PathSegment,
+          Type};
 
 pub struct Overrides {
     pub name: Option<String>,
+    pub sql_type: Option<String>,
+    pub ordered: bool,
+    pub null: bool,
+    pub flags: bool,
+    pub crate_: Option<Path>,
+    pub types: Option<Path>,
+    pub runtime: Option<Path>,
+    pub with: Option<Path>,
+    pub from: Option<Type>,
+    pub try_from: Option<Type>,
+    pub into: Option<Type>,
+    pub validate: Option<Path>,
+    pub set: Option<Ident>,
+    pub repr: Option<String>,
+    pub value: Option<i64>,
+    pub bit: Option<i64>,
+    pub width: Option<i64>,
+    pub code: Option<char>,
+    names: Vec<String>,
 }
 
 impl Overrides {
     pub fn extract(attrs: &[Attribute]) -> Result<Overrides, String> {
         let mut overrides = Overrides {
             name: None,
+            sql_type: None,
+            ordered: false,
+            null: false,
+            flags: false,
+            crate_: None,
+            types: None,
+            runtime: None,
+            with: None,
+            from: None,
+            try_from: None,
+            into: None,
+            validate: None,
+            set: None,
+            repr: None,
+            value: None,
+            bit: None,
+            width: None,
+            code: None,
+            names: vec![],
         };
 
         for attr in attrs {
@@ -26,24 +65,171 @@ impl Overrides {
             };
 
             for item in &list.nested {
+                if let NestedMeta::Meta(ref meta) = *item {
+                    overrides.names.push(meta.name().to_string());
+                }
+
                 match *item {
-                    NestedMeta::Meta(Meta::NameValue(ref meta)) => {
-                        if meta.ident.as_ref() != "name" {
-                            return Err(format!("unknown override `{}`", meta.ident.as_ref()));
-                        }
+                    NestedMeta::Meta(Meta::NameValue(ref meta))
+                        if meta.ident == "value" || meta.ident == "bit" || meta.ident == "width" =>
+                    {
+                        let value = match meta.lit {
+                            Lit::Int(ref i) if i.value() <= i64::MAX as u64 => {
+                                i.value() as i64
+                            }
+                            Lit::Str(ref s) => {
+                                s.value()
+                                    .parse()
+                                    .map_err(|_| format!("invalid integer `{}`", s.value()))?
+                            }
+                            _ => return Err("expected an integer literal".to_owned()),
+                        };
 
+                        match meta.ident.as_ref() {
+                            "value" => overrides.value = Some(value),
+                            "bit" => overrides.bit = Some(value),
+                            _ => overrides.width = Some(value),
+                        }
+                    }
+                    NestedMeta::Meta(Meta::NameValue(ref meta)) if meta.ident == "code" => {
+                        let code = match meta.lit {
+                            Lit::Char(ref c) => c.value(),
+                            _ => return Err("expected a character literal".to_owned()),
+                        };
+                        if !code.is_ascii() {
+                            return Err(format!("code `{}` is not an ASCII character", code));
+                        }
+                        overrides.code = Some(code);
+                    }
+                    NestedMeta::Meta(Meta::NameValue(ref meta)) => {
                         let value = match meta.lit {
                             Lit::Str(ref s) => s.value(),
                             _ => return Err("expected a string literal".to_owned()),
                         };
 
-                        overrides.name = Some(value);
+                        match meta.ident.as_ref() {
+                            "name" => overrides.name = Some(value),
+                            "sql_type" => overrides.sql_type = Some(value),
+                            "repr" => overrides.repr = Some(value),
+                            "crate" => {
+                                let path = syn::parse_str(&value)
+                                    .map_err(|_| format!("invalid crate path `{}`", value))?;
+                                overrides.crate_ = Some(path);
+                            }
+                            "types" => {
+                                let path = syn::parse_str(&value)
+                                    .map_err(|_| format!("invalid types path `{}`", value))?;
+                                overrides.types = Some(path);
+                            }
+                            "runtime" => {
+                                let path = syn::parse_str(&value)
+                                    .map_err(|_| format!("invalid runtime path `{}`", value))?;
+                                overrides.runtime = Some(path);
+                            }
+                            "with" => {
+                                let path = syn::parse_str(&value)
+                                    .map_err(|_| format!("invalid module path `{}`", value))?;
+                                overrides.with = Some(path);
+                            }
+                            "from" => overrides.from = Some(parse_type(&value)?),
+                            "try_from" => overrides.try_from = Some(parse_type(&value)?),
+                            "into" => overrides.into = Some(parse_type(&value)?),
+                            "set" => {
+                                let ident = syn::parse_str(&value)
+                                    .map_err(|_| format!("invalid set name `{}`", value))?;
+                                overrides.set = Some(ident);
+                            }
+                            "validate" => {
+                                let path = syn::parse_str(&value)
+                                    .map_err(|_| format!("invalid function path `{}`", value))?;
+                                overrides.validate = Some(path);
+                            }
+                            name => return Err(format!("unknown override `{}`", name)),
+                        }
                     },
+                    NestedMeta::Meta(Meta::Word(ref ident)) => {
+                        match ident.as_ref() {
+                            "ordered" => overrides.ordered = true,
+                            "null" => overrides.null = true,
+                            "flags" => overrides.flags = true,
+                            name => return Err(format!("unknown override `{}`", name)),
+                        }
+                    }
                     _ => return Err("expected a name-value meta item".to_owned()),
                 }
             }
         }
 
+        if overrides.from.is_some() && overrides.try_from.is_some() {
+            return Err("`from` and `try_from` may not both be specified".to_owned());
+        }
+        if overrides.validate.is_some()
+            && (overrides.from.is_some() || overrides.try_from.is_some() || overrides.into.is_some())
+        {
+            return Err("`validate` may not be combined with `from`, `try_from` or `into`".to_owned());
+        }
+
         Ok(overrides)
     }
+
+    /// Checks that only the specified overrides are present, naming the position of the attribute
+    /// otherwise.
+    pub fn check(&self, allowed: &[&str], position: &str) -> Result<(), String> {
+        match self.names.iter().find(|name| !allowed.contains(&&***name)) {
+            Some(name) => Err(format!("`{}` may not be applied to {}", name, position)),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that the overrides of a type, and of the field of a domain, are used by its kind.
+    pub fn check_container(&self, data: &Data) -> Result<(), String> {
+        let (kind, position): (&[&str], _) = match *data {
+            Data::Enum(_) => (&["repr", "ordered", "set"], "enums"),
+            Data::Struct(DataStruct { fields: Fields::Named(_), .. }) if self.flags => {
+                (&["flags", "repr", "width"], "flags")
+            }
+            Data::Struct(DataStruct { fields: Fields::Unnamed(ref fields), .. }) => {
+                for field in &fields.unnamed {
+                    Overrides::extract(&field.attrs)?.check(&["sql_type"], "domain fields")?;
+                }
+                (&["validate"], "domains")
+            }
+            Data::Struct(_) => (&["validate"], "composites"),
+            Data::Union(_) => return Ok(()),
+        };
+
+        let mut allowed = vec!["name", "crate", "types", "runtime", "from", "try_from", "into"];
+        allowed.extend_from_slice(kind);
+        self.check(&allowed, position)
+    }
+
+    /// Returns the path of the module defining `ToSql`, `FromSql` and `Type`.
+    ///
+    /// This is the `types` module of the crate, except for postgres-types itself, which defines
+    /// them at its root.
+    pub fn types_path(&self) -> Path {
+        if let Some(ref path) = self.types {
+            return path.clone();
+        }
+
+        let mut path = match self.crate_ {
+            Some(ref path) => path.clone(),
+            None => syn::parse_str("::postgres").unwrap(),
+        };
+        if path.segments.last().unwrap().value().ident != "postgres_types" {
+            path.segments.push(PathSegment::from(Ident::from("types")));
+        }
+        path
+    }
+
+    pub fn runtime_path(&self) -> Path {
+        match self.runtime {
+            Some(ref path) => path.clone(),
+            None => syn::parse_str("::postgres_derive_runtime").unwrap(),
+        }
+    }
+}
+
+fn parse_type(value: &str) -> Result<Type, String> {
+    syn::parse_str(value).map_err(|_| format!("invalid type `{}`", value))
 }
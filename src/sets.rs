@@ -0,0 +1,152 @@
+use syn::{Ident, Data, DeriveInput, Visibility};
+use quote::Tokens;
+
+use enums::{self, Repr, Variant};
+use overrides::Overrides;
+
+// The set type is defined by its own derive, as the ToSql and FromSql derives each only add an
+// impl for it, and can't tell whether the other is also applied to the enum.
+pub fn expand_derive_set(input: DeriveInput) -> Result<Tokens, String> {
+    let overrides = Overrides::extract(&input.attrs)?;
+    overrides.check_container(&input.data)?;
+
+    let set = match overrides.set {
+        Some(ref set) => set,
+        None => return Err("#[derive(PgSet)] requires a #[postgres(set = \"...\")]".to_owned()),
+    };
+    let data = match input.data {
+        Data::Enum(ref data) => data,
+        _ => return Err("#[derive(PgSet)] may only be applied to enums".to_owned()),
+    };
+
+    let repr = Repr::parse(overrides.repr.as_deref())?;
+    let (variants, null) = enums::parse_variants(data, repr)?;
+    if null.is_some() {
+        return Err("sets may not contain a #[postgres(null)] variant".to_owned());
+    }
+
+    Ok(definition(&input.vis, set, &input.ident, &variants))
+}
+
+/// Defines a set of the variants of an enum, stored as a bitset indexed by declaration order.
+pub fn definition(vis: &Visibility, set: &Ident, ident: &Ident, variants: &[Variant]) -> Tokens {
+    let num_variants = variants.len();
+    let words = (num_variants + 63) / 64;
+    let idents = &variants.iter().map(|_| ident).collect::<Vec<_>>();
+    let variant_idents = &variants.iter().map(|v| &v.ident).collect::<Vec<_>>();
+    let indices = &(0..num_variants).collect::<Vec<_>>();
+    let doc = format!("A set of `{}` values.", ident);
+
+    let lookup = quote! {
+        fn index(value: &#ident) -> usize {
+            match *value {
+                #(
+                    #idents::#variant_idents => #indices,
+                )*
+            }
+        }
+
+        fn variant(index: usize) -> ::std::option::Option<#ident> {
+            match index {
+                #(
+                    #indices => ::std::option::Option::Some(#idents::#variant_idents),
+                )*
+                _ => ::std::option::Option::None,
+            }
+        }
+    };
+
+    let update = quote! {
+        /// Adds a value to the set, returning `false` if it was already present.
+        pub fn insert(&mut self, value: #ident) -> bool {
+            let index = #set::index(&value);
+            let mask = 1 << (index % 64);
+            let new = self.bits[index / 64] & mask == 0;
+            self.bits[index / 64] |= mask;
+            new
+        }
+
+        /// Removes a value from the set, returning `true` if it was present.
+        pub fn remove(&mut self, value: &#ident) -> bool {
+            let index = #set::index(value);
+            let mask = 1 << (index % 64);
+            let present = self.bits[index / 64] & mask != 0;
+            self.bits[index / 64] &= !mask;
+            present
+        }
+    };
+
+    let query = quote! {
+        /// Determines if the set contains a value.
+        pub fn contains(&self, value: &#ident) -> bool {
+            let index = #set::index(value);
+            self.bits[index / 64] & (1 << (index % 64)) != 0
+        }
+
+        /// Returns the number of values in the set.
+        pub fn len(&self) -> usize {
+            self.bits.iter().map(|word| word.count_ones() as usize).sum()
+        }
+
+        /// Determines if the set is empty.
+        pub fn is_empty(&self) -> bool {
+            self.bits.iter().all(|&word| word == 0)
+        }
+
+        /// Returns an iterator over the values in the set, in declaration order.
+        pub fn iter<'a>(&'a self) -> impl ::std::iter::Iterator<Item = #ident> + 'a {
+            (0..#num_variants)
+                .filter(move |&index| self.bits[index / 64] & (1 << (index % 64)) != 0)
+                .filter_map(#set::variant)
+        }
+    };
+
+    let traits = quote! {
+        impl ::std::default::Default for #set {
+            fn default() -> #set {
+                #set::new()
+            }
+        }
+
+        impl ::std::iter::FromIterator<#ident> for #set {
+            fn from_iter<I>(iter: I) -> #set
+            where
+                I: ::std::iter::IntoIterator<Item = #ident>,
+            {
+                let mut set = #set::new();
+                for value in iter {
+                    set.insert(value);
+                }
+                set
+            }
+        }
+
+        impl ::std::fmt::Debug for #set {
+            fn fmt(&self, fmt: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                fmt.debug_set().entries(self.iter()).finish()
+            }
+        }
+    };
+
+    quote! {
+        #[doc = #doc]
+        #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+        #vis struct #set {
+            bits: [u64; #words],
+        }
+
+        #[allow(dead_code)]
+        impl #set {
+            /// Creates an empty set.
+            pub fn new() -> #set {
+                #set { bits: [0; #words] }
+            }
+
+            #lookup
+            #update
+            #query
+        }
+
+        #traits
+    }
+}
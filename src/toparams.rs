@@ -0,0 +1,52 @@
+use std::iter;
+use syn::{DeriveInput, Data, DataStruct, Fields};
+use quote::Tokens;
+
+use composites::Field;
+use overrides::Overrides;
+
+pub fn expand_derive_toparams(input: DeriveInput) -> Result<Tokens, String> {
+    let overrides = Overrides::extract(&input.attrs)?;
+    overrides.check_container(&input.data)?;
+
+    // The runtime's ToParams trait returns postgres 0.15 trait objects
+    if cfg!(feature = "postgres-types") {
+        return Err("#[derive(ToParams)] is not supported with the postgres-types feature"
+            .to_owned());
+    }
+    if overrides.crate_.is_some() || overrides.types.is_some() {
+        return Err("#[derive(ToParams)] does not support `crate` or `types`".to_owned());
+    }
+
+    let types = overrides.types_path();
+    let runtime = overrides.runtime_path();
+
+    let fields = match input.data {
+        Data::Struct(DataStruct { fields: Fields::Named(ref fields), .. }) => {
+            fields.named.iter().map(Field::parse).collect::<Result<Vec<_>, _>>()?
+        }
+        _ => {
+            return Err("#[derive(ToParams)] may only be applied to structs with named fields"
+                .to_owned())
+        }
+    };
+
+    let field_names = fields.iter().map(|f| &f.name);
+    let field_idents = fields.iter().map(|f| &f.ident);
+    let type_paths = iter::repeat(&types);
+
+    let ident = &input.ident;
+    let out = quote! {
+        impl #runtime::ToParams for #ident {
+            fn columns() -> &'static [&'static str] {
+                &[#(#field_names),*]
+            }
+
+            fn to_params(&self) -> ::std::vec::Vec<&dyn (#types::ToSql)> {
+                ::std::vec![#(&self.#field_idents as &dyn (#type_paths::ToSql)),*]
+            }
+        }
+    };
+
+    Ok(out)
+}
@@ -1,30 +1,67 @@
 use std::iter;
-use syn::{Ident, DeriveInput, Data, DataStruct, Fields};
+use syn::{self, Ident, Path, DeriveInput, Data, DataStruct, Fields};
 use quote::Tokens;
 
 use accepts;
 use composites::Field;
-use enums::Variant;
+use enums::{self, Repr, Variant};
+use flags::Flags;
 use overrides::Overrides;
+use traits;
 
 pub fn expand_derive_tosql(input: DeriveInput) -> Result<Tokens, String> {
     let overrides = Overrides::extract(&input.attrs)?;
+    overrides.check_container(&input.data)?;
+
+    let types = overrides.types_path();
+    let runtime = overrides.runtime_path();
+
+    if let Some(ref into) = overrides.into {
+        return Ok(conversion_impl(&types, &input.ident, into));
+    }
 
     let name = overrides.name.unwrap_or_else(|| input.ident.to_string());
 
+    let mut set_tokens = None;
     let (accepts_body, to_sql_body) = match input.data {
         Data::Enum(ref data) => {
-            let variants = data.variants.iter().map(Variant::parse).collect::<Result<Vec<_>, _>>()?;
-            (accepts::enum_body(&name, &variants), enum_body(&input.ident, &variants))
+            let repr = Repr::parse(overrides.repr.as_deref())?;
+            let (variants, null) = enums::parse_variants(data, repr)?;
+            enums::check_ordered(overrides.ordered, repr, &variants, &null)?;
+            let ident = &input.ident;
+            if let Some(ref set) = overrides.set {
+                if null.is_some() {
+                    return Err("sets may not contain a #[postgres(null)] variant".to_owned());
+                }
+                set_tokens = Some(set_impl(&types, set, ident));
+            }
+            let null_arm = null.map(|v| {
+                let variant_ident = &v.ident;
+                quote! {
+                    #ident::#variant_ident => {
+                        return ::std::result::Result::Ok(#types::IsNull::Yes);
+                    }
+                }
+            });
+            (accepts::enum_body(&types, &name, &variants, overrides.ordered, repr),
+             if repr.is_int() {
+                 int_enum_body(&types, ident, &variants, &null_arm, repr)?
+             } else {
+                 enum_body(&types, ident, &variants, &null_arm)
+             })
+        }
+        Data::Struct(DataStruct { fields: Fields::Named(ref fields), .. }) if overrides.flags => {
+            let flags = Flags::parse(overrides.repr.as_deref(), overrides.width, fields)?;
+            (accepts::flags_body(&types, &flags), flags_body(&types, &flags))
         }
         Data::Struct(DataStruct { fields: Fields::Unnamed(ref fields), .. }) if fields.unnamed.len() == 1 => {
             let field = fields.unnamed.first().unwrap().into_value();
-            (accepts::domain_body(&name, &field), domain_body())
+            (accepts::domain_body(&types, &name, &field), domain_body(&types, field))
         }
         Data::Struct(DataStruct { fields: Fields::Named(ref fields), .. }) => {
             let fields = fields.named.iter().map(Field::parse).collect::<Result<Vec<_>, _>>()?;
-            (accepts::composite_body(&name, "ToSql", &fields),
-             composite_body(&fields))
+            (accepts::composite_body(&types, &runtime, &name, "ToSql", &fields),
+             composite_body(&types, &runtime, &fields))
         }
         _ => {
             return Err("#[derive(ToSql)] may only be applied to structs, single field tuple \
@@ -32,31 +69,109 @@ pub fn expand_derive_tosql(input: DeriveInput) -> Result<Tokens, String> {
         }
     };
 
+    let validate = overrides.validate.as_ref().map(|validate| {
+        quote!(#runtime::ValidationError::__check(_type.name(), #validate(self))?;)
+    });
+
     let ident = &input.ident;
+    let buf = traits::to_sql_buf(&types);
+    let error = traits::error();
     let out = quote! {
-        impl ::postgres::types::ToSql for #ident {
+        impl #types::ToSql for #ident {
             fn to_sql(&self,
-                      _type: &::postgres::types::Type,
-                      buf: &mut ::std::vec::Vec<u8>)
-                      -> ::std::result::Result<::postgres::types::IsNull,
-                                               ::std::boxed::Box<::std::error::Error +
-                                                                 ::std::marker::Sync +
-                                                                 ::std::marker::Send>> {
+                      _type: &#types::Type,
+                      buf: &mut #buf)
+                      -> ::std::result::Result<#types::IsNull, #error> {
+                #validate
                 #to_sql_body
             }
 
-            fn accepts(type_: &::postgres::types::Type) -> bool {
+            fn accepts(type_: &#types::Type) -> bool {
                 #accepts_body
             }
 
-            to_sql_checked!();
+            fn to_sql_checked(&self,
+                              type_: &#types::Type,
+                              buf: &mut #buf)
+                              -> ::std::result::Result<#types::IsNull, #error> {
+                #types::__to_sql_checked(self, type_, buf)
+            }
         }
+
+        #set_tokens
     };
 
     Ok(out)
 }
 
-fn enum_body(ident: &Ident, variants: &[Variant]) -> Tokens {
+// Converts a clone of the value to `into` and encodes that, like serde's `into`. Domains over a
+// type `into` accepts are encoded as that type.
+fn conversion_impl(types: &Path, ident: &Ident, into: &syn::Type) -> Tokens {
+    let to_sql_base = accepts::domain_base(types, "ToSql", into, quote!(_type));
+    let accepts_base = accepts::domain_base(types, "ToSql", into, quote!(type_));
+    let buf = traits::to_sql_buf(types);
+    let error = traits::error();
+    quote! {
+        impl #types::ToSql for #ident {
+            fn to_sql(&self,
+                      _type: &#types::Type,
+                      buf: &mut #buf)
+                      -> ::std::result::Result<#types::IsNull, #error> {
+                let type_ = if <#into as #types::ToSql>::accepts(_type) {
+                    _type
+                } else {
+                    #to_sql_base.unwrap_or(_type)
+                };
+                let value: #into = ::std::convert::Into::into(::std::clone::Clone::clone(self));
+                #types::ToSql::to_sql(&value, type_, buf)
+            }
+
+            fn accepts(type_: &#types::Type) -> bool {
+                <#into as #types::ToSql>::accepts(type_) || #accepts_base.is_some()
+            }
+
+            fn to_sql_checked(&self,
+                              type_: &#types::Type,
+                              buf: &mut #buf)
+                              -> ::std::result::Result<#types::IsNull, #error> {
+                #types::__to_sql_checked(self, type_, buf)
+            }
+        }
+    }
+}
+
+// Sets are encoded as an array of their values. The set type itself is defined by the PgSet
+// derive.
+fn set_impl(types: &Path, set: &Ident, ident: &Ident) -> Tokens {
+    let buf = traits::to_sql_buf(types);
+    let error = traits::error();
+
+    quote! {
+        impl #types::ToSql for #set {
+            fn to_sql(&self,
+                      _type: &#types::Type,
+                      buf: &mut #buf)
+                      -> ::std::result::Result<#types::IsNull, #error> {
+                let values = self.iter().collect::<::std::vec::Vec<#ident>>();
+                #types::ToSql::to_sql(&values, _type, buf)
+            }
+
+            fn accepts(type_: &#types::Type) -> bool {
+                <::std::vec::Vec<#ident> as #types::ToSql>::accepts(type_)
+            }
+
+            fn to_sql_checked(&self,
+                              type_: &#types::Type,
+                              buf: &mut #buf)
+                              -> ::std::result::Result<#types::IsNull, #error> {
+                #types::__to_sql_checked(self, type_, buf)
+            }
+        }
+    }
+}
+
+fn enum_body(types: &Path, ident: &Ident, variants: &[Variant], null_arm: &Option<Tokens>)
+             -> Tokens {
     let idents = iter::repeat(ident);
     let variant_idents = variants.iter().map(|v| &v.ident);
     let variant_names = variants.iter().map(|v| &v.name);
@@ -66,74 +181,145 @@ fn enum_body(ident: &Ident, variants: &[Variant]) -> Tokens {
             #(
                 #idents::#variant_idents => #variant_names,
             )*
+            #null_arm
         };
 
         buf.extend_from_slice(s.as_bytes());
-        ::std::result::Result::Ok(::postgres::types::IsNull::No)
+        ::std::result::Result::Ok(#types::IsNull::No)
+    }
+}
+
+fn int_enum_body(types: &Path,
+                 ident: &Ident,
+                 variants: &[Variant],
+                 null_arm: &Option<Tokens>,
+                 repr: Repr)
+                 -> Result<Tokens, String> {
+    let ty = Ident::from(repr.rust_type().unwrap());
+    let base = accepts::base_type(types, quote!(_type));
+    let arms = repr.values(variants)?.into_iter().zip(variants).map(|(value, v)| {
+        let variant_ident = &v.ident;
+        let value = repr.literal(value);
+        quote!(#ident::#variant_ident => #value,)
+    });
+
+    Ok(quote! {
+        let value: #ty = match *self {
+            #(#arms)*
+            #null_arm
+        };
+
+        #types::ToSql::to_sql(&value, #base, buf)
+    })
+}
+
+fn flags_body(types: &Path, flags: &Flags) -> Tokens {
+    let idents = flags.flags.iter().map(|f| &f.ident);
+
+    match flags.repr.int_type() {
+        Some(ty) => {
+            let base = accepts::base_type(types, quote!(_type));
+            let masks = flags.flags.iter().map(|f| flags.repr.mask(f.bit));
+
+            quote! {
+                let mut value: #ty = 0;
+                #(
+                    if self.#idents {
+                        value |= #masks;
+                    }
+                )*
+
+                #types::ToSql::to_sql(&value, #base, buf)
+            }
+        }
+        None => {
+            let header = (flags.width as i32).to_be_bytes();
+            let header = header.iter();
+            let len = (flags.width + 7) / 8;
+            let bytes = flags.flags.iter().map(|f| f.bit / 8);
+            let masks = flags.flags.iter().map(|f| 0x80u8 >> (f.bit % 8));
+
+            quote! {
+                let mut bits = ::std::vec![0u8; #len];
+                #(
+                    if self.#idents {
+                        bits[#bytes] |= #masks;
+                    }
+                )*
+
+                buf.extend_from_slice(&[#(#header),*]);
+                buf.extend_from_slice(&bits);
+                ::std::result::Result::Ok(#types::IsNull::No)
+            }
+        }
     }
 }
 
-fn domain_body() -> Tokens {
+fn domain_body(types: &Path, field: &syn::Field) -> Tokens {
+    let base = accepts::domain_base(types, "ToSql", &field.ty, quote!(_type));
+
     quote! {
-        let type_ = match *_type.kind() {
-            ::postgres::types::Kind::Domain(ref type_) => type_,
-            _ => unreachable!(),
+        let type_ = match #base {
+            ::std::option::Option::Some(type_) => type_,
+            ::std::option::Option::None => {
+                return ::std::result::Result::Err(::std::convert::Into::into(
+                    ::std::format!("type `{}` is not a domain", _type)));
+            }
         };
 
-        ::postgres::types::ToSql::to_sql(&self.0, type_, buf)
+        #types::ToSql::to_sql(&self.0, type_, buf)
     }
 }
 
-fn composite_body(fields: &[Field]) -> Tokens {
+fn composite_body(types: &Path, runtime: &Path, fields: &[Field]) -> Tokens {
     let field_names = fields.iter().map(|f| &f.name);
-    let field_idents = fields.iter().map(|f| &f.ident);
+    let writes = fields.iter().enumerate().map(|(position, f)| {
+        let field_ident = &f.ident;
+        let to_sql = match f.with {
+            Some(ref with) => quote!(#with::to_sql),
+            None => quote!(#types::ToSql::to_sql),
+        };
+        quote! {
+            #position => #to_sql(&self.#field_ident, field.type_(), buf),
+        }
+    });
 
     quote! {
-        fn write_be_i32<W>(buf: &mut W, n: i32) -> ::std::io::Result<()>
-            where W: ::std::io::Write
-        {
-            let be = [(n >> 24) as u8, (n >> 16) as u8, (n >> 8) as u8, n as u8];
-            buf.write_all(&be)
-        }
+        static PLANS: #runtime::record::PlanCache = #runtime::record::PlanCache::new();
 
         let fields = match *_type.kind() {
-            ::postgres::types::Kind::Composite(ref fields) => fields,
-            _ => unreachable!(),
+            #types::Kind::Composite(ref fields) => fields,
+            _ => {
+                return ::std::result::Result::Err(::std::convert::Into::into(
+                    ::std::format!("type `{}` is not a composite", _type)));
+            }
         };
 
-        write_be_i32(buf, fields.len() as i32)?;
+        let plan = PLANS.encode_plan(_type.name(),
+                                     _type.oid(),
+                                     &[#(#field_names),*],
+                                     fields.iter().map(|f| f.name()))?;
 
-        for field in fields {
-            write_be_i32(buf, field.type_().oid() as i32)?;
+        buf.extend_from_slice(&#runtime::record::header(fields.len())?);
 
+        for (field, &position) in fields.iter().zip(plan.iter()) {
             let base = buf.len();
-            write_be_i32(buf, 0)?;
-            let r = match field.name() {
-                #(
-                    #field_names => {
-                        ::postgres::types::ToSql::to_sql(&self.#field_idents,
-                                                         field.type_(),
-                                                         buf)
-                    }
-                )*
-                _ => unreachable!(),
-            };
-
-            let count = match r? {
-                ::postgres::types::IsNull::Yes => -1,
-                ::postgres::types::IsNull::No => {
-                    let len = buf.len() - base - 4;
-                    if len > i32::max_value() as usize {
-                        return ::std::result::Result::Err(
-                            ::std::convert::Into::into("value too large to transmit"));
-                    }
-                    len as i32
+            buf.extend_from_slice(&#runtime::record::field_header(field.type_().oid()));
+            let r = match position {
+                #(#writes)*
+                _ => {
+                    return ::std::result::Result::Err(::std::convert::Into::into(
+                        ::std::format!("unexpected field `{}`", field.name())));
                 }
             };
 
-            write_be_i32(&mut &mut buf[base..base + 4], count)?;
+            let is_null = match r? {
+                #types::IsNull::Yes => true,
+                #types::IsNull::No => false,
+            };
+            #runtime::record::finish_field(&mut buf[base..], is_null)?;
         }
 
-        ::std::result::Result::Ok(::postgres::types::IsNull::No)
+        ::std::result::Result::Ok(#types::IsNull::No)
     }
 }
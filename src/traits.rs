@@ -0,0 +1,45 @@
+//! The signatures of the generated `ToSql` and `FromSql` impls.
+//!
+//! By default these match the traits of postgres 0.15. The `postgres-types` feature instead
+//! targets the traits of the postgres-types crate (used by postgres 0.16+ and tokio-postgres
+//! 0.5+), where `FromSql` borrows from its buffer and `ToSql` writes into a `BytesMut`.
+use syn::{Ident, Path};
+use quote::Tokens;
+
+// `dyn ::std::...` parses as the path `dyn::std::...` in the 2015 edition, so the trait is
+// parenthesized.
+pub fn error() -> Tokens {
+    quote! {
+        ::std::boxed::Box<dyn (::std::error::Error) + ::std::marker::Sync + ::std::marker::Send>
+    }
+}
+
+#[cfg(not(feature = "postgres-types"))]
+pub fn to_sql_buf(_: &Path) -> Tokens {
+    quote!(::std::vec::Vec<u8>)
+}
+
+#[cfg(feature = "postgres-types")]
+pub fn to_sql_buf(types: &Path) -> Tokens {
+    quote!(#types::private::BytesMut)
+}
+
+#[cfg(not(feature = "postgres-types"))]
+pub fn from_sql_impl(types: &Path, ident: &Ident) -> Tokens {
+    quote!(impl #types::FromSql for #ident)
+}
+
+#[cfg(feature = "postgres-types")]
+pub fn from_sql_impl(types: &Path, ident: &Ident) -> Tokens {
+    quote!(impl<'a> #types::FromSql<'a> for #ident)
+}
+
+#[cfg(not(feature = "postgres-types"))]
+pub fn from_sql_buf() -> Tokens {
+    quote!(&[u8])
+}
+
+#[cfg(feature = "postgres-types")]
+pub fn from_sql_buf() -> Tokens {
+    quote!(&'a [u8])
+}
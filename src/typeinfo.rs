@@ -0,0 +1,158 @@
+use std::iter;
+use syn::{self, Path, DeriveInput, Data, DataStruct, Fields};
+use quote::Tokens;
+
+use composites::Field;
+use enums::{self, Repr, Variant};
+use overrides::Overrides;
+
+pub fn expand_derive_typeinfo(input: DeriveInput) -> Result<Tokens, String> {
+    let overrides = Overrides::extract(&input.attrs)?;
+    overrides.check_container(&input.data)?;
+
+    let types = overrides.types_path();
+    let runtime = overrides.runtime_path();
+    let name = overrides.name.unwrap_or_else(|| input.ident.to_string());
+
+    // The runtime's TypeInfo holds accepts functions taking a postgres 0.15 Type
+    if cfg!(feature = "postgres-types") {
+        return Err("#[derive(PgTypeInfo)] is not supported with the postgres-types feature"
+            .to_owned());
+    }
+    if overrides.crate_.is_some() || overrides.types.is_some() {
+        return Err("#[derive(PgTypeInfo)] does not support `crate` or `types`".to_owned());
+    }
+    if overrides.flags {
+        return Err("#[derive(PgTypeInfo)] does not support #[postgres(flags)]".to_owned());
+    }
+
+    let kind = match input.data {
+        Data::Enum(ref data) => {
+            let repr = Repr::parse(overrides.repr.as_deref())?;
+            let (variants, null) = enums::parse_variants(data, repr)?;
+            enums::check_ordered(overrides.ordered, repr, &variants, &null)?;
+            match repr.rust_type() {
+                // Enums stored as other types are described as the domain created by CreateType
+                Some(rust_type) => {
+                    let ty = syn::parse_str(rust_type).unwrap();
+                    domain_kind(&types, &runtime, &ty)
+                }
+                None => enum_kind(&runtime, &variants, overrides.ordered),
+            }
+        }
+        Data::Struct(DataStruct { fields: Fields::Unnamed(ref fields), .. }) if fields.unnamed.len() == 1 => {
+            let field = fields.unnamed.first().unwrap().into_value();
+            domain_kind(&types, &runtime, &field.ty)
+        }
+        Data::Struct(DataStruct { fields: Fields::Named(ref fields), .. }) => {
+            let fields = fields.named.iter().map(Field::parse).collect::<Result<Vec<_>, _>>()?;
+            composite_kind(&types, &runtime, &fields)
+        }
+        _ => {
+            return Err("#[derive(PgTypeInfo)] may only be applied to structs, single field tuple \
+                        structs, and enums".to_owned())
+        }
+    };
+
+    let ident = &input.ident;
+    let rust_name = ident.to_string();
+    let out = quote! {
+        impl #runtime::PgTypeInfo for #ident {
+            fn type_info() -> &'static #runtime::info::TypeInfo {
+                static INFO: #runtime::info::TypeInfo =
+                    #runtime::info::TypeInfo {
+                        name: #name,
+                        rust_name: #rust_name,
+                        kind: #kind,
+                    };
+
+                &INFO
+            }
+        }
+    };
+
+    Ok(out)
+}
+
+fn enum_kind(runtime: &Path, variants: &[Variant], ordered: bool) -> Tokens {
+    let variant_names = variants.iter().map(|v| &v.name);
+    let variant_rust_names = variants.iter().map(|v| v.ident.to_string());
+    let runtimes = iter::repeat(runtime);
+
+    quote! {
+        #runtime::info::TypeKind::Enum(
+            #runtime::info::EnumInfo {
+                variants: &[
+                    #(
+                        #runtimes::info::VariantInfo {
+                            name: #variant_names,
+                            rust_name: #variant_rust_names,
+                        },
+                    )*
+                ],
+                ordered: #ordered,
+            })
+    }
+}
+
+fn domain_kind(types: &Path, runtime: &Path, ty: &syn::Type) -> Tokens {
+    let rust_type = type_name(ty);
+
+    quote! {
+        #runtime::info::TypeKind::Domain(
+            #runtime::info::DomainInfo {
+                rust_type: #rust_type,
+                accepts: <#ty as #types::FromSql>::accepts,
+            })
+    }
+}
+
+fn composite_kind(types: &Path, runtime: &Path, fields: &[Field]) -> Tokens {
+    let field_names = fields.iter().map(|f| &f.name);
+    let field_rust_names = fields.iter().map(|f| f.ident.to_string());
+    let field_rust_types = fields.iter().map(|f| type_name(&f.type_));
+    let accepts = fields.iter().map(|f| {
+        let field_type = &f.type_;
+        match f.with {
+            Some(ref with) => quote!(#with::accepts),
+            None => quote!(<#field_type as #types::FromSql>::accepts),
+        }
+    });
+    let runtimes = iter::repeat(runtime);
+
+    quote! {
+        #runtime::info::TypeKind::Composite(&[
+            #(
+                #runtimes::info::FieldInfo {
+                    name: #field_names,
+                    rust_name: #field_rust_names,
+                    rust_type: #field_rust_types,
+                    accepts: #accepts,
+                },
+            )*
+        ])
+    }
+}
+
+// Tokens are displayed with a space between each one, so tidy up the common cases to match the
+// way types are normally written.
+fn type_name(ty: &syn::Type) -> String {
+    let mut name = quote!(#ty).to_string();
+    for &(from, to) in &[
+        (" <", "<"),
+        ("< ", "<"),
+        (" >", ">"),
+        (" ::", "::"),
+        (":: ", "::"),
+        (" ,", ","),
+        (" ;", ";"),
+        ("& ", "&"),
+        ("[ ", "["),
+        (" ]", "]"),
+        ("( ", "("),
+        (" )", ")"),
+    ] {
+        name = name.replace(from, to);
+    }
+    name
+}
@@ -0,0 +1,37 @@
+#[macro_use]
+extern crate criterion;
+extern crate postgres;
+extern crate postgres_derive_stress;
+
+use criterion::{black_box, Criterion};
+use postgres::types::{FromSql, Kind, Type};
+use postgres_derive_stress::{Enum10, Enum200, Enum2000};
+
+fn enum_type(name: &str, labels: &[&str]) -> Type {
+    let labels = labels.iter().map(|l| l.to_string()).collect();
+    Type::_new(name.to_owned(), 16384, Kind::Enum(labels), "public".to_owned())
+}
+
+macro_rules! bench_from_sql {
+    ($c:expr, $ty:ident, $name:expr) => {{
+        let type_ = enum_type($name, $ty::LABELS);
+        let labels = $ty::LABELS;
+
+        $c.bench_function(concat!("from_sql ", $name), move |b| {
+            b.iter(|| {
+                for label in labels {
+                    $ty::from_sql(&type_, black_box(label.as_bytes())).unwrap();
+                }
+            })
+        });
+    }};
+}
+
+fn from_sql(c: &mut Criterion) {
+    bench_from_sql!(c, Enum10, "enum10");
+    bench_from_sql!(c, Enum200, "enum200");
+    bench_from_sql!(c, Enum2000, "enum2000");
+}
+
+criterion_group!(benches, from_sql);
+criterion_main!(benches);
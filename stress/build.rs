@@ -0,0 +1,70 @@
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    let mut out = String::new();
+    for &size in &[10, 200, 2000] {
+        write_enum(&mut out, size);
+    }
+    for &size in &[500, 1000] {
+        write_composite(&mut out, size);
+    }
+
+    let path = Path::new(&env::var("OUT_DIR").unwrap()).join("fixtures.rs");
+    fs::write(path, out).unwrap();
+}
+
+// Labels are three letter codes, like those of the `country_code` and `currency` enums this is
+// modeled on.
+fn label(i: usize) -> String {
+    let letter = |n: usize| (b'a' + (n % 26) as u8) as char;
+    [letter(i / 676), letter(i / 26), letter(i)].iter().collect()
+}
+
+fn write_enum(out: &mut String, size: usize) {
+    writeln!(out, "#[derive(Debug, Clone, Copy, PartialEq, ToSql, FromSql)]").unwrap();
+    writeln!(out, "#[postgres(name = \"enum{}\")]", size).unwrap();
+    writeln!(out, "pub enum Enum{} {{", size).unwrap();
+    for i in 0..size {
+        writeln!(out, "    #[postgres(name = \"{}\")]", label(i)).unwrap();
+        writeln!(out, "    V{},", i).unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+
+    writeln!(out, "impl Enum{} {{", size).unwrap();
+    writeln!(out, "    pub const LABELS: &'static [&'static str] = &[").unwrap();
+    for i in 0..size {
+        writeln!(out, "        \"{}\",", label(i)).unwrap();
+    }
+    writeln!(out, "    ];").unwrap();
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+}
+
+// The field types cycle through these, paired with their OIDs.
+const FIELD_TYPES: &[(&str, u32)] = &[("i32", 23), ("String", 25), ("Option<i32>", 23), ("bool", 16)];
+
+fn write_composite(out: &mut String, size: usize) {
+    writeln!(out, "#[derive(Debug, Default, PartialEq, ToSql, FromSql)]").unwrap();
+    writeln!(out, "#[postgres(name = \"composite{}\")]", size).unwrap();
+    writeln!(out, "pub struct Composite{} {{", size).unwrap();
+    for i in 0..size {
+        let (type_, _) = FIELD_TYPES[i % FIELD_TYPES.len()];
+        writeln!(out, "    pub field_{}: {},", i, type_).unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+
+    writeln!(out, "impl Composite{} {{", size).unwrap();
+    writeln!(out, "    pub const FIELDS: &'static [(&'static str, u32)] = &[").unwrap();
+    for i in 0..size {
+        let (_, oid) = FIELD_TYPES[i % FIELD_TYPES.len()];
+        writeln!(out, "        (\"field_{}\", {}),", i, oid).unwrap();
+    }
+    writeln!(out, "    ];").unwrap();
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+}
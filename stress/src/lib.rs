@@ -0,0 +1,10 @@
+//! Generated types used to stress test and benchmark postgres-derive.
+//!
+//! The fixtures are written by the build script, and include enums of 10, 200 and 2000 labels and
+//! composites of 500 and 1000 fields.
+#[macro_use]
+extern crate postgres_derive;
+extern crate postgres_derive_runtime;
+extern crate postgres;
+
+include!(concat!(env!("OUT_DIR"), "/fixtures.rs"));
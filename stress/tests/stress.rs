@@ -0,0 +1,57 @@
+extern crate postgres;
+extern crate postgres_derive_stress;
+
+use postgres::types::{Field, FromSql, Kind, ToSql, Type};
+use postgres_derive_stress::{Composite1000, Composite500, Enum2000};
+
+fn composite_type(name: &str, fields: &[(&str, u32)]) -> Type {
+    let fields = fields
+        .iter()
+        .map(|&(name, oid)| Field::new(name.to_owned(), Type::from_oid(oid).unwrap()))
+        .collect();
+    Type::_new(name.to_owned(), 16384, Kind::Composite(fields), "public".to_owned())
+}
+
+#[test]
+fn enum2000() {
+    let labels = Enum2000::LABELS.iter().map(|l| l.to_string()).collect();
+    let type_ = Type::_new("enum2000".to_owned(), 16384, Kind::Enum(labels), "public".to_owned());
+    assert!(<Enum2000 as ToSql>::accepts(&type_));
+    assert!(<Enum2000 as FromSql>::accepts(&type_));
+
+    for label in Enum2000::LABELS {
+        let value = Enum2000::from_sql(&type_, label.as_bytes()).unwrap();
+        let mut buf = vec![];
+        value.to_sql(&type_, &mut buf).unwrap();
+        assert_eq!(buf, label.as_bytes());
+    }
+}
+
+#[test]
+fn composite500() {
+    let type_ = composite_type("composite500", Composite500::FIELDS);
+    assert!(<Composite500 as ToSql>::accepts(&type_));
+    assert!(<Composite500 as FromSql>::accepts(&type_));
+
+    let value = Composite500 {
+        field_1: "foobar".to_owned(),
+        field_2: Some(100),
+        field_499: true,
+        ..Composite500::default()
+    };
+    let mut buf = vec![];
+    value.to_sql(&type_, &mut buf).unwrap();
+    assert_eq!(Composite500::from_sql(&type_, &buf).unwrap(), value);
+}
+
+#[test]
+fn composite1000() {
+    let type_ = composite_type("composite1000", Composite1000::FIELDS);
+    assert!(<Composite1000 as ToSql>::accepts(&type_));
+    assert!(<Composite1000 as FromSql>::accepts(&type_));
+
+    let value = Composite1000::default();
+    let mut buf = vec![];
+    value.to_sql(&type_, &mut buf).unwrap();
+    assert_eq!(Composite1000::from_sql(&type_, &buf).unwrap(), value);
+}
@@ -0,0 +1,88 @@
+#[macro_use]
+extern crate postgres_derive;
+extern crate postgres_derive_runtime;
+extern crate postgres;
+
+use postgres_derive_runtime::PgTypeInfo;
+use postgres_derive_runtime::info::TypeKind;
+
+#[test]
+fn enum_() {
+    #[derive(PgTypeInfo)]
+    #[postgres(name = "mood")]
+    #[allow(dead_code)]
+    enum Mood {
+        #[postgres(name = "sad")]
+        Sad,
+        Happy,
+    }
+
+    let info = Mood::type_info();
+    assert_eq!(info.name, "mood");
+    assert_eq!(info.rust_name, "Mood");
+    assert_eq!(info.kind.name(), "enum");
+
+    let enum_ = match info.kind {
+        TypeKind::Enum(ref enum_) => enum_,
+        _ => panic!("expected an enum"),
+    };
+    assert!(!enum_.ordered);
+    assert_eq!(
+        enum_.variants.iter().map(|v| (v.name, v.rust_name)).collect::<Vec<_>>(),
+        &[("sad", "Sad"), ("Happy", "Happy")]
+    );
+}
+
+#[test]
+fn domain() {
+    #[derive(PgTypeInfo)]
+    #[allow(dead_code)]
+    struct SessionId(Vec<u8>);
+
+    let info = SessionId::type_info();
+    assert_eq!(info.name, "SessionId");
+    assert_eq!(info.rust_name, "SessionId");
+    assert_eq!(info.kind.name(), "domain");
+
+    let domain = match info.kind {
+        TypeKind::Domain(ref domain) => domain,
+        _ => panic!("expected a domain"),
+    };
+    assert_eq!(domain.rust_type, "Vec<u8>");
+}
+
+#[test]
+fn composite() {
+    #[derive(PgTypeInfo)]
+    #[postgres(name = "inventory_item")]
+    #[allow(dead_code)]
+    struct InventoryItem {
+        name: String,
+        #[postgres(name = "supplier_id")]
+        supplier: i32,
+        price: Option<f64>,
+        tags: Vec<::std::string::String>,
+    }
+
+    let info = InventoryItem::type_info();
+    assert_eq!(info.name, "inventory_item");
+    assert_eq!(info.rust_name, "InventoryItem");
+    assert_eq!(info.kind.name(), "composite");
+
+    let fields = match info.kind {
+        TypeKind::Composite(fields) => fields,
+        _ => panic!("expected a composite"),
+    };
+    assert_eq!(
+        fields
+            .iter()
+            .map(|f| (f.name, f.rust_name, f.rust_type))
+            .collect::<Vec<_>>(),
+        &[
+            ("name", "name", "String"),
+            ("supplier_id", "supplier", "i32"),
+            ("price", "price", "Option<f64>"),
+            ("tags", "tags", "Vec<::std::string::String>"),
+        ]
+    );
+}